@@ -1,84 +1,299 @@
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs::Permissions;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::Duration;
-use std::{fmt, fs, io};
+use std::{fmt, fs, io, thread};
 
-use clap::Parser;
-use dbus::blocking::Connection;
+use clap::{Parser, Subcommand};
+use dbus::blocking::{Connection, Proxy};
+use ini::Ini;
+
+use gdevd::{RgbColor, MIN_SPEED_MILLIS};
 
 /// Change background lights of Logitech gaming devices
+///
+/// Shows status of devices when called without command.
 #[derive(Parser)]
+struct Cli {
+    /// Send command only to device with serial number (see `gdevctl list`)
+    #[arg(long, global = true)]
+    device: Option<String>,
+    #[command(subcommand)]
+    action: Option<Action>,
+}
+
+#[derive(Subcommand)]
 #[command(rename_all = "kebab")]
-enum Cli {
+enum Action {
     /// Set color for keyboard sector
     Color {
-        /// Hex string for color
+        /// Hex string for color (rrggbb or rrggbbww for devices with white LEDs)
+        /// or color name (like red, orange or cyan)
         color: String,
         /// sector index
         sector: Option<u8>,
     },
+    /// Set fixed colors of left, middle and right zone (G203 LIGHTSYNC)
+    Triple {
+        /// Hex string or color name for left zone
+        left: String,
+        /// Hex string or color name for middle zone
+        middle: String,
+        /// Hex string or color name for right zone
+        right: String,
+    },
+    /// Color sectors with a gradient from first to last sector
+    Gradient {
+        /// Hex string or color name of first sector
+        start: String,
+        /// Hex string or color name of last sector
+        end: String,
+    },
     /// Apply breathe effect
     Breathe {
         /// Hex string for color
         color: String,
         /// animation time step in milliseconds
-        /// (minimum value depends on device, default value depends on device)
+        /// (at least 32, minimum value depends on device, default value depends on device)
+        #[arg(value_parser = clap::value_parser!(u16).range(MIN_SPEED_MILLIS as i64..))]
         time_step: u16,
         /// brightness (must be greater or equal than 0 and less or equal than 100; default is 100)
+        #[arg(value_parser = clap::value_parser!(u8).range(..=100))]
         brightness: u8,
     },
+    /// Apply reactive effect that lights up pressed keys
+    Reactive {
+        /// Hex string for color
+        color: String,
+        /// animation time step in milliseconds
+        /// (at least 32, minimum value depends on device)
+        #[arg(value_parser = clap::value_parser!(u16).range(MIN_SPEED_MILLIS as i64..))]
+        time_step: u16,
+    },
     /// Apply cycle effect
     Cycle {
         /// animation time step in milliseconds
-        /// (minimum value depends on device, default value depends on device)
+        /// (at least 32, minimum value depends on device, default value depends on device)
+        #[arg(value_parser = clap::value_parser!(u16).range(MIN_SPEED_MILLIS as i64..))]
+        time_step: u16,
+        /// brightness (must be greater or equal than 0 and less or equal than 100; default is 100)
+        #[arg(value_parser = clap::value_parser!(u8).range(..=100))]
+        brightness: u8,
+    },
+    /// Apply blend effect
+    Blend {
+        /// animation time step in milliseconds
+        /// (at least 32, minimum value depends on device, default value depends on device)
+        #[arg(value_parser = clap::value_parser!(u16).range(MIN_SPEED_MILLIS as i64..))]
         time_step: u16,
         /// brightness (must be greater or equal than 0 and less or equal than 100; default is 100)
+        #[arg(value_parser = clap::value_parser!(u8).range(..=100))]
         brightness: u8,
     },
+    /// Enable or disable effect shown when device is powered on
+    StartEffect {
+        /// whether start effect is shown (true or false)
+        #[arg(action = clap::ArgAction::Set)]
+        state: bool,
+    },
     /// Apply wave effect
     Wave {
         /// direction of effect (left-to-right, right-to-left, center-to-edge, edge-to-center;
         ///   default is left-to-right)
         direction: String,
         /// animation time step in milliseconds
-        /// (minimum value depends on device, default value depends on device)
+        /// (at least 32, minimum value depends on device, default value depends on device)
+        #[arg(value_parser = clap::value_parser!(u16).range(MIN_SPEED_MILLIS as i64..))]
         time_step: u16,
         /// brightness (must be greater or equal than 0 and less or equal than 100; default is 100)
+        #[arg(value_parser = clap::value_parser!(u8).range(..=100))]
+        brightness: u8,
+    },
+    /// Play animation from file
+    ///
+    /// Each line of the file contains the time in milliseconds to show the frame followed
+    /// by a hex color for every sector. Empty lines and lines starting with `#` are ignored.
+    Play {
+        /// File with frames
+        frames: PathBuf,
+        /// Repeat animation until interrupted
+        #[arg(long = "loop")]
+        repeat: bool,
+    },
+    /// Select or show profile stored on device
+    OnboardProfile {
+        /// profile slot to select (starting with 1); shows active slots if omitted
+        slot: Option<u8>,
+    },
+    /// Turn lighting off
+    Off,
+    /// Light sectors one after another to show which sector index is where
+    TestPattern,
+    /// Stop effect animated by daemon and keep current colors
+    Stop,
+    /// Apply device section of an ini file to all devices at once
+    ///
+    /// The file uses the keys of device sections in the config file. Keys outside of
+    /// a section are used, otherwise the first section.
+    ApplyFile {
+        /// path of ini file
+        path: PathBuf,
+    },
+    /// Change brightness of current effect
+    Brightness {
+        /// brightness (must be greater or equal than 0 and less or equal than 100)
+        #[arg(value_parser = clap::value_parser!(u8).range(..=100))]
         brightness: u8,
     },
+    /// Set sensitivity of mice
+    Dpi {
+        /// dots per inch (range depends on device)
+        dpi: u16,
+    },
+    /// Send raw HID++ report to all devices (requires allow_raw in [gdevd] config section)
+    ///
+    /// Unvalidated reports can leave devices in an unexpected state until they are
+    /// replugged. Use only if you know the HID++ feature of your device.
+    Feature {
+        /// HID++ feature index
+        feature: u8,
+        /// hex string of function byte and parameters (e.g. 3a0001)
+        params: String,
+    },
+    /// Temporarily dim all devices without changing saved config
+    Dim {
+        /// brightness in percent applied on top of saved brightness
+        /// (must be greater or equal than 0 and less or equal than 100)
+        #[arg(value_parser = clap::value_parser!(u8).range(..=100))]
+        brightness: u8,
+    },
+    /// Remove temporary dimming
+    Undim,
+    /// Switch to named profile from config
+    Profile {
+        /// name of profile (config sections like [G213:<name>])
+        name: String,
+    },
+    /// Save lighting shown by `play` or a stopped software effect to config
+    Save,
     /// Reapply saved effect
-    Refresh,
+    Refresh {
+        /// only reapply for device with serial number
+        serial: Option<String>,
+    },
+    /// Turn lighting of device off or restore saved lighting
+    Toggle {
+        /// serial number of device
+        serial: String,
+    },
+    /// Ignore commands for device until unlocked
+    Lock {
+        /// serial number of device
+        serial: String,
+    },
+    /// Accept commands for device again
+    Unlock {
+        /// serial number of device
+        serial: String,
+    },
     /// List drivers
-    ListDrivers,
+    ListDrivers {
+        /// Show whether driver is stable or experimental
+        #[arg(long)]
+        verbose: bool,
+        /// Print drivers as JSON array
+        #[arg(long)]
+        json: bool,
+    },
     /// List devices
-    List,
+    List {
+        /// Print devices as JSON array
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show status of devices
+    Status,
+    /// Print debug information of devices for bug reports
+    Info,
+    /// Report ignored or invalid entries of the daemon config
+    CheckConfig,
+    /// List effects supported by devices
+    Capabilities {
+        /// Read effects from device firmware if supported
+        #[arg(long)]
+        from_device: bool,
+    },
+    /// Print HID++ feature index and feature ID of each feature of device
+    Probe {
+        /// serial number of device
+        serial: String,
+    },
     /// Install daemon as systemd service
     InstallService {
         /// Prefix for service installation
-        #[structopt(long, default_value = "/usr/local")]
+        #[arg(long, default_value = "/usr/local")]
         prefix: PathBuf,
     },
     /// Uninstall daemon as systemd service
     UninstallService {
         /// Prefix of service installation
-        #[structopt(long, default_value = "/usr/local")]
+        #[arg(long, default_value = "/usr/local")]
         prefix: PathBuf,
     },
 }
 
+impl Action {
+    /// Whether command can be sent to a single device with `--device`
+    fn is_device_scoped(&self) -> bool {
+        matches!(
+            self,
+            Action::Color { .. }
+                | Action::Triple { .. }
+                | Action::Breathe { .. }
+                | Action::Reactive { .. }
+                | Action::Cycle { .. }
+                | Action::Blend { .. }
+                | Action::StartEffect { .. }
+                | Action::Wave { .. }
+                | Action::Off
+                | Action::Brightness { .. }
+                | Action::Dpi { .. }
+                | Action::Refresh { serial: None }
+        )
+    }
+}
+
+/// Model, serial number, sector count and supported features of device
+type DeviceCapabilities = (String, String, u8, Vec<String>);
+
 fn main() {
-    match _main() {
-        Ok(_) => {}
-        Err(err) => {
-            eprintln!("ERROR: {err}")
+    let err = match _main() {
+        Ok(()) => return,
+        Err(err) => err,
+    };
+    match err.downcast_ref::<dbus::Error>() {
+        Some(err) if err.name() == Some("de.richardliebscher.gdevd.Error.DeviceNotFound") => {
+            eprintln!(
+                "ERROR: {}, see `gdevctl list` for connected devices",
+                err.message().unwrap_or_default()
+            )
+        }
+        Some(err)
+            if err.name() == Some("de.richardliebscher.gdevd.Error.Unsupported")
+                || err.name() == Some("de.richardliebscher.gdevd.Error.PartialFailure") =>
+        {
+            eprintln!("ERROR: {}", err.message().unwrap_or_default())
         }
+        _ => eprintln!("ERROR: {err}"),
     }
+    std::process::exit(1);
 }
 
 fn _main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
     simple_logger::init()?;
 
     // DBus
@@ -89,83 +304,651 @@ fn _main() -> Result<(), Box<dyn Error>> {
         Duration::from_millis(5000),
     );
 
-    match Cli::parse() {
-        Cli::Color {
+    let action = cli.action.unwrap_or(Action::Status);
+    if cli.device.is_some() && !action.is_device_scoped() {
+        return Err("--device is not supported by this command".into());
+    }
+
+    match (action, cli.device) {
+        (
+            Action::Color {
+                color,
+                sector: Some(sector),
+            },
+            Some(serial),
+        ) => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "color_sector_on",
+                (&serial as &str, &color as &str, sector),
+            )?;
+        }
+        (Action::Color { color, sector: _ }, Some(serial)) => {
+            let color = RgbColor::parse(&color)?.to_hex();
+            send_to(&devices, &serial, "static-all", &[("color", color)])?;
+        }
+        (
+            Action::Triple {
+                left,
+                middle,
+                right,
+            },
+            Some(serial),
+        ) => {
+            send_to(
+                &devices,
+                &serial,
+                "triple",
+                // Config properties only accept hex colors
+                &[
+                    ("color-0", RgbColor::parse(&left)?.to_hex()),
+                    ("color-1", RgbColor::parse(&middle)?.to_hex()),
+                    ("color-2", RgbColor::parse(&right)?.to_hex()),
+                ],
+            )?;
+        }
+        (
+            Action::Breathe {
+                color,
+                time_step,
+                brightness,
+            },
+            Some(serial),
+        ) => {
+            send_to(
+                &devices,
+                &serial,
+                "breathe",
+                &[
+                    ("color", RgbColor::parse(&color)?.to_hex()),
+                    ("speed", time_step.to_string()),
+                    ("brightness", brightness.to_string()),
+                ],
+            )?;
+        }
+        (
+            Action::Cycle {
+                time_step,
+                brightness,
+            },
+            Some(serial),
+        ) => {
+            send_to(
+                &devices,
+                &serial,
+                "cycle",
+                &[
+                    ("speed", time_step.to_string()),
+                    ("brightness", brightness.to_string()),
+                ],
+            )?;
+        }
+        (
+            Action::Blend {
+                time_step,
+                brightness,
+            },
+            Some(serial),
+        ) => {
+            send_to(
+                &devices,
+                &serial,
+                "blend",
+                &[
+                    ("speed", time_step.to_string()),
+                    ("brightness", brightness.to_string()),
+                ],
+            )?;
+        }
+        (Action::StartEffect { state }, Some(serial)) => {
+            send_to(
+                &devices,
+                &serial,
+                "startEffect",
+                &[("state", state.to_string())],
+            )?;
+        }
+        (Action::Reactive { color, time_step }, Some(serial)) => {
+            send_to(
+                &devices,
+                &serial,
+                "reactive",
+                &[
+                    ("color", RgbColor::parse(&color)?.to_hex()),
+                    ("speed", time_step.to_string()),
+                ],
+            )?;
+        }
+        (
+            Action::Wave {
+                direction,
+                time_step,
+                brightness,
+            },
+            Some(serial),
+        ) => {
+            send_to(
+                &devices,
+                &serial,
+                "wave",
+                &[
+                    ("direction", direction),
+                    ("speed", time_step.to_string()),
+                    ("brightness", brightness.to_string()),
+                ],
+            )?;
+        }
+        (Action::Off, Some(serial)) => {
+            send_to(&devices, &serial, "off", &[])?;
+        }
+        (Action::Brightness { brightness }, Some(serial)) => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "set_brightness_on",
+                (&serial as &str, brightness),
+            )?;
+        }
+        (Action::Dpi { dpi }, Some(serial)) => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "dpi_on",
+                (&serial as &str, dpi),
+            )?;
+        }
+        (Action::Refresh { serial: None }, Some(serial)) => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "refresh_device",
+                (&serial as &str,),
+            )?;
+        }
+        (action, _) => run_broadcast(&devices, action)?,
+    }
+
+    Ok(())
+}
+
+/// Send command described by config properties to device with serial number
+fn send_to(
+    devices: &Proxy<'_, &Connection>,
+    serial: &str,
+    kind: &str,
+    params: &[(&str, String)],
+) -> Result<(), dbus::Error> {
+    let params: HashMap<&str, &str> = params
+        .iter()
+        .map(|(key, value)| (*key, value as &str))
+        .collect();
+    devices.method_call(
+        "de.richardliebscher.gdevd.GDeviceManager",
+        "send_to",
+        (serial, kind, params),
+    )
+}
+
+fn run_broadcast(devices: &Proxy<'_, &Connection>, action: Action) -> Result<(), Box<dyn Error>> {
+    match action {
+        Action::Color {
             color,
             sector: Some(sector),
         } => {
-            devices.method_call(
+            devices.method_call::<(), _, _, _>(
                 "de.richardliebscher.gdevd.GDeviceManager",
                 "color_sector",
                 (&color as &str, sector),
             )?;
         }
-        Cli::Color { color, sector: _ } => {
-            devices.method_call(
+        Action::Color { color, sector: _ } => {
+            devices.method_call::<(), _, _, _>(
                 "de.richardliebscher.gdevd.GDeviceManager",
                 "color_sectors",
                 (&color as &str,),
             )?;
         }
-        Cli::Breathe {
+        Action::Triple {
+            left,
+            middle,
+            right,
+        } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "triple_color",
+                (&left as &str, &middle as &str, &right as &str),
+            )?;
+        }
+        Action::Gradient { start, end } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "gradient",
+                (&start as &str, &end as &str),
+            )?;
+        }
+        Action::Breathe {
             color,
             time_step,
             brightness,
         } => {
-            devices.method_call(
+            devices.method_call::<(), _, _, _>(
                 "de.richardliebscher.gdevd.GDeviceManager",
                 "breathe",
                 (color, time_step, brightness),
             )?;
         }
-        Cli::Cycle {
+        Action::Cycle {
             time_step,
             brightness,
         } => {
-            devices.method_call(
+            devices.method_call::<(), _, _, _>(
                 "de.richardliebscher.gdevd.GDeviceManager",
                 "cycle",
                 (time_step, brightness),
             )?;
         }
-        Cli::Wave {
+        Action::Blend {
+            time_step,
+            brightness,
+        } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "blend",
+                (time_step, brightness),
+            )?;
+        }
+        Action::StartEffect { state } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "start_effect",
+                (state,),
+            )?;
+        }
+        Action::Reactive { color, time_step } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "reactive",
+                (&color as &str, time_step),
+            )?;
+        }
+        Action::Wave {
             direction,
             time_step,
             brightness,
         } => {
-            devices.method_call(
+            devices.method_call::<(), _, _, _>(
                 "de.richardliebscher.gdevd.GDeviceManager",
                 "wave",
                 (&direction as &str, time_step, brightness),
             )?;
         }
-        Cli::Refresh => {
-            devices.method_call("de.richardliebscher.gdevd.GDeviceManager", "refresh", ())?;
+        Action::Play { frames, repeat } => {
+            let frames = read_frames(&frames)?;
+            loop {
+                for frame in &frames {
+                    devices.method_call::<(), _, _, _>(
+                        "de.richardliebscher.gdevd.GDeviceManager",
+                        "push_frame",
+                        (&frame.colors,),
+                    )?;
+                    thread::sleep(frame.duration);
+                }
+                if !repeat {
+                    break;
+                }
+            }
         }
-        Cli::ListDrivers => {
-            let drivers: (Vec<(String,)>,) = devices.method_call(
+        Action::OnboardProfile { slot: Some(slot) } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "select_onboard_profile",
+                (slot,),
+            )?;
+        }
+        Action::OnboardProfile { slot: None } => {
+            let profiles: (Vec<(String, u8)>,) = devices.method_call(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "get_onboard_profiles",
+                (),
+            )?;
+            for profile in profiles.0 {
+                println!("{}: {}", profile.0, profile.1);
+            }
+        }
+        Action::Off => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "off",
+                (),
+            )?;
+        }
+        Action::ApplyFile { path } => {
+            let ini = Ini::load_from_file(&path)?;
+            let props = ini
+                .section(None::<String>)
+                .filter(|props| !props.is_empty())
+                .or_else(|| ini.iter().find_map(|(name, props)| name.map(|_| props)))
+                .ok_or_else(|| format!("{} has no keys", path.display()))?;
+            let section: HashMap<&str, &str> = props.iter().collect();
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "apply",
+                (section,),
+            )?;
+        }
+        Action::Stop => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "stop",
+                (),
+            )?;
+        }
+        Action::TestPattern => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "test_pattern",
+                (),
+            )?;
+        }
+        Action::Brightness { brightness } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "set_brightness",
+                (brightness,),
+            )?;
+        }
+        Action::Dpi { dpi } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "dpi",
+                (dpi,),
+            )?;
+        }
+        Action::Feature { feature, params } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "set_feature_report",
+                (feature, parse_hex_bytes(&params)?),
+            )?;
+        }
+        Action::Dim { brightness } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "set_global_brightness_override",
+                (brightness,),
+            )?;
+        }
+        Action::Undim => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "clear_global_brightness_override",
+                (),
+            )?;
+        }
+        Action::Profile { name } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "apply_profile",
+                (&name as &str,),
+            )?;
+        }
+        Action::Probe { serial } => {
+            let (features,): (Vec<(u8, u16)>,) = devices.method_call(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "probe",
+                (&serial as &str,),
+            )?;
+            for (index, feature) in features {
+                println!("{:#04x}: {:#06x}", index, feature);
+            }
+        }
+        Action::Save => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "save",
+                (),
+            )?;
+        }
+        Action::Refresh { serial: None } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "refresh",
+                (),
+            )?;
+        }
+        Action::Refresh {
+            serial: Some(serial),
+        } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "refresh_device",
+                (&serial as &str,),
+            )?;
+        }
+        Action::Toggle { serial } => {
+            let (on,): (bool,) = devices.method_call(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "toggle_lighting",
+                (&serial as &str,),
+            )?;
+            println!("Lighting {}", if on { "on" } else { "off" });
+        }
+        Action::Lock { serial } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "lock",
+                (&serial as &str,),
+            )?;
+        }
+        Action::Unlock { serial } => {
+            devices.method_call::<(), _, _, _>(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "unlock",
+                (&serial as &str,),
+            )?;
+        }
+        Action::ListDrivers { verbose, json } => {
+            let drivers: (Vec<(String, String)>,) = devices.method_call(
                 "de.richardliebscher.gdevd.GDeviceManager",
                 "list_drivers",
                 (),
             )?;
-            for driver in drivers.0 {
-                println!("{}", driver.0);
+            if json {
+                let drivers: Vec<String> = drivers
+                    .0
+                    .iter()
+                    .map(|driver| {
+                        format!(
+                            "{{\"model\":{},\"stability\":{}}}",
+                            json_string(&driver.0),
+                            json_string(&driver.1)
+                        )
+                    })
+                    .collect();
+                println!("[{}]", drivers.join(","));
+            } else {
+                for driver in drivers.0 {
+                    if verbose {
+                        println!("{} ({})", driver.0, driver.1);
+                    } else {
+                        println!("{}", driver.0);
+                    }
+                }
             }
         }
-        Cli::List => {
-            let devices: (Vec<(String, String)>,) =
+        Action::List { json } => {
+            let devices: (Vec<(String, String, String, String)>,) =
                 devices.method_call("de.richardliebscher.gdevd.GDeviceManager", "list", ())?;
-            for device in devices.0 {
-                println!("{}: {}", device.0, device.1);
+            if json {
+                let devices: Vec<String> = devices
+                    .0
+                    .iter()
+                    .map(|device| {
+                        format!(
+                            "{{\"model\":{},\"serial\":{},\"type\":{},\"firmware\":{}}}",
+                            json_string(&device.0),
+                            json_string(&device.1),
+                            json_string(&device.3),
+                            json_string(&device.2)
+                        )
+                    })
+                    .collect();
+                println!("[{}]", devices.join(","));
+            } else {
+                for device in devices.0 {
+                    if device.2.is_empty() {
+                        println!("{}: {}", device.0, device.1);
+                    } else {
+                        println!("{}: {} (firmware {})", device.0, device.1, device.2);
+                    }
+                }
+            }
+        }
+        Action::Status => print_status(devices)?,
+        Action::Info => {
+            let (info,): (Vec<(String, String)>,) = devices.method_call(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "get_debug_info",
+                (),
+            )?;
+            for (serial, info) in info {
+                println!("{}: {}", serial, info);
+            }
+        }
+        Action::CheckConfig => {
+            let (warnings,): (Vec<String>,) = devices.method_call(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "config_warnings",
+                (),
+            )?;
+            if warnings.is_empty() {
+                println!("Config is valid");
+            }
+            for warning in warnings {
+                println!("{}", warning);
+            }
+        }
+        Action::Capabilities { from_device } => {
+            let (capabilities,): (Vec<DeviceCapabilities>,) = devices.method_call(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "get_capabilities",
+                (),
+            )?;
+            for (model, serial, sectors, _) in capabilities {
+                println!("{} [{}]: {} sectors", model, serial, sectors);
+            }
+            let (effects,): (Vec<(String, Vec<String>)>,) = devices.method_call(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "get_supported_effects",
+                (from_device,),
+            )?;
+            for (serial, effects) in effects {
+                println!("{}: {}", serial, effects.join(", "));
+            }
+            let (stages,): (Vec<(String, u8)>,) = devices.method_call(
+                "de.richardliebscher.gdevd.GDeviceManager",
+                "get_dpi_stages",
+                (),
+            )?;
+            for (serial, stages) in stages {
+                println!("{}: up to {} DPI stages", serial, stages);
             }
         }
-        Cli::InstallService { prefix } => install_service(&prefix)?,
-        Cli::UninstallService { prefix } => uninstall_service(&prefix)?,
+        Action::InstallService { prefix } => install_service(&prefix)?,
+        Action::UninstallService { prefix } => uninstall_service(&prefix)?,
     }
 
     Ok(())
 }
 
+fn print_status(devices: &Proxy<'_, &Connection>) -> Result<(), Box<dyn Error>> {
+    let (devices,): (Vec<(String, String, String)>,) =
+        devices.method_call("de.richardliebscher.gdevd.GDeviceManager", "get_state", ())?;
+    if devices.is_empty() {
+        println!("No devices found");
+    }
+    for device in devices {
+        println!("{} [{}]: {}", device.0, device.1, device.2);
+    }
+    Ok(())
+}
+
+struct Frame {
+    duration: Duration,
+    colors: Vec<String>,
+}
+
+/// Quote string as JSON string literal
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn parse_hex_bytes(hex: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(format!("Invalid hex string: {}", hex).into());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| format!("Invalid hex string: {}", hex).into())
+        })
+        .collect()
+}
+
+fn read_frames(path: &Path) -> Result<Vec<Frame>, Box<dyn Error>> {
+    let mut frames: Vec<Frame> = vec![];
+    for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let millis: u64 = parts
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|err| format!("line {}: invalid duration: {err}", i + 1))?;
+        let colors: Vec<String> = parts.map(|color| color.to_string()).collect();
+        if let Some(color) = colors
+            .iter()
+            .find(|color| RgbColor::from_hex(color).is_err())
+        {
+            return Err(format!("line {}: invalid color {color}", i + 1).into());
+        }
+        if let Some(first) = frames.first() {
+            if first.colors.len() != colors.len() {
+                return Err(format!(
+                    "line {}: expected {} colors, got {}",
+                    i + 1,
+                    first.colors.len(),
+                    colors.len()
+                )
+                .into());
+            }
+        }
+
+        frames.push(Frame {
+            duration: Duration::from_millis(millis),
+            colors,
+        });
+    }
+
+    if frames.is_empty() {
+        return Err("no frames in file".into());
+    }
+    Ok(frames)
+}
+
 static SERVICE_FILES: &[(&str, &str)] = &[
     (
         "/etc/dbus-1/system.d/gdevd-dbus.conf",
@@ -195,7 +978,7 @@ fn install_service(prefix: &Path) -> Result<(), io::Error> {
 
     let prefix_str = prefix
         .to_str()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "invalid prefix path"))?;
+        .ok_or_else(|| io::Error::other("invalid prefix path"))?;
 
     for (path, content) in SERVICE_FILES {
         install_file(path, content.replace("$$PREFIX$$", prefix_str).as_bytes())?;
@@ -246,8 +1029,8 @@ fn uninstall_service(prefix: &Path) -> Result<(), io::Error> {
         run_command(Command::new("systemctl").arg("stop").arg("gdevd"))
     })?;
 
-    uninstall_file(&prefix.join("bin/gdevd"))?;
-    uninstall_file(&prefix.join("bin/gdevctl"))?;
+    uninstall_file(prefix.join("bin/gdevd"))?;
+    uninstall_file(prefix.join("bin/gdevctl"))?;
 
     for (path, _) in SERVICE_FILES {
         uninstall_file(path)?;
@@ -270,10 +1053,7 @@ fn uninstall_file(path: impl AsRef<Path>) -> Result<(), io::Error> {
 fn run_command(cmd: &mut Command) -> io::Result<()> {
     let out = cmd.output()?;
     if !out.status.success() {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            String::from_utf8_lossy(&out.stderr),
-        ))
+        Err(io::Error::other(String::from_utf8_lossy(&out.stderr)))
     } else {
         Ok(())
     }
@@ -1,22 +1,58 @@
 #[macro_use]
 extern crate log;
 
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::error::Error;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+use clap::Parser;
 use dbus::blocking::Connection;
+use dbus::message::MatchRule;
 use dbus::MethodErr;
-use dbus_tree::{Factory, Interface, MTSync};
+use dbus_tree::{Factory, Interface, MTSync, Signal};
+use ini::Properties;
 use rusb::UsbContext;
 use signal_hook::consts::TERM_SIGNALS;
 use signal_hook::iterator::Signals;
 
-use gdevd::Command::{Breathe, ColorSector, Cycle, Wave};
-use gdevd::{Brightness, GDeviceManager, GDeviceManagerEvent, RgbColor};
+use gdevd::config::Config;
+use gdevd::Command::{
+    Breathe, ColorSector, ColorSectorRgbw, Cycle, SelectOnboardProfile, SetFeatureReport, Wave,
+};
+use gdevd::{
+    Brightness, Command, CommandError, GDeviceManager, GDeviceManagerEvent, RgbColor, RgbwColor,
+    Speed, MIN_SPEED_MILLIS,
+};
+
+/// Daemon to control background LEDs of Logitech gaming devices
+#[derive(Parser)]
+struct Args {
+    /// Path of config file (default is /etc/gdevd.conf)
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Provide DBus service on session bus instead of system bus
+    #[arg(long)]
+    session: bool,
+    /// Apply config to devices once and exit without starting the DBus service
+    ///
+    /// Config is not reapplied on changes, hotplugging or resume in this mode.
+    #[arg(long)]
+    oneshot: bool,
+}
+
+impl Args {
+    fn load_config(&self) -> Config {
+        match &self.config {
+            Some(path) => Config::load_from(path),
+            None => Config::load(),
+        }
+    }
+}
 
 #[derive(Copy, Clone, Default, Debug)]
 struct TreeData;
@@ -30,88 +66,309 @@ impl dbus_tree::DataType for TreeData {
     type Signal = ();
 }
 
+const ERROR_DEVICE_NOT_FOUND: &str = "de.richardliebscher.gdevd.Error.DeviceNotFound";
+const ERROR_UNSUPPORTED: &str = "de.richardliebscher.gdevd.Error.Unsupported";
+const ERROR_PARTIAL_FAILURE: &str = "de.richardliebscher.gdevd.Error.PartialFailure";
+
+/// Map command error to DBus error, with specific names for missing devices and features
+fn command_err(err: &CommandError) -> MethodErr {
+    match err {
+        CommandError::DeviceNotFound(_) => (ERROR_DEVICE_NOT_FOUND, err.to_string()).into(),
+        CommandError::Unsupported { .. } => (ERROR_UNSUPPORTED, err.to_string()).into(),
+        CommandError::PartialFailure(_) => (ERROR_PARTIAL_FAILURE, err.to_string()).into(),
+        _ => MethodErr::invalid_arg(err),
+    }
+}
+
 fn parse_brightness(brightness: u8) -> Result<Option<Brightness>, MethodErr> {
-    match Brightness::try_from(brightness) {
-        Ok(brightness) => Ok(Some(brightness)),
-        Err(_) => Err(MethodErr::invalid_arg(
-            "brightness must be between 0 and 100",
-        )),
+    Brightness::try_from(brightness)
+        .map(Some)
+        .map_err(|err| MethodErr::invalid_arg(&err))
+}
+
+/// Speeds of methods are milliseconds, the manager converts them to units of each device
+fn parse_speed(speed: u16) -> Result<Speed, MethodErr> {
+    if speed < MIN_SPEED_MILLIS {
+        return Err(MethodErr::invalid_arg("speed"));
     }
+    Ok(Speed::from(speed))
 }
 
-fn create_interface() -> Interface<MTSync<TreeData>, TreeData> {
-    // TODO: missing commands: start, blend, dpi
+fn parse_color(color: &str, sector: Option<u8>) -> Result<Command, MethodErr> {
+    if color.len() == 8 {
+        let rgbw = RgbwColor::from_hex(color).map_err(|_err| MethodErr::invalid_arg("color"))?;
+        Ok(ColorSectorRgbw(rgbw, sector))
+    } else {
+        let rgb = RgbColor::parse(color).map_err(|_err| MethodErr::invalid_arg("color"))?;
+        Ok(ColorSector(rgb, sector))
+    }
+}
+
+const INTERFACE_NAME: &str = "de.richardliebscher.gdevd.GDeviceManager";
+const OBJECT_PATH: &str = "/devices";
+
+fn create_interface(
+    device_list_changed: Arc<Signal<TreeData>>,
+) -> Interface<MTSync<TreeData>, TreeData> {
     let f = Factory::new_sync::<TreeData>();
-    f.interface("de.richardliebscher.gdevd.GDeviceManager", ())
+    f.interface(INTERFACE_NAME, ())
+        .add_s(device_list_changed)
         .add_m(
             f.method("list_drivers", (), move |m| {
                 let manager = m.path.get_data();
-                let drivers: Vec<(&str,)> = manager
+                let drivers: Vec<(&str, String)> = manager
                     .list_drivers()
                     .iter()
-                    .map(|driver| (*driver,))
+                    .map(|driver| (driver.model, driver.stability.to_string()))
                     .collect();
                 Ok(vec![m.msg.method_return().append1(drivers)])
             })
-            .outarg::<&[(&str,)], _>("drivers"),
+            .outarg::<&[(&str, &str)], _>("drivers"),
         )
         .add_m(
             f.method("list", (), move |m| {
                 let manager = m.path.get_data();
                 let devices = manager.list();
-                let devices_info: Vec<(&str, &str)> = devices
+                let devices_info: Vec<(&str, &str, &str, String)> = devices
                     .iter()
-                    .map(|dev| (dev.model, &dev.serial as &str))
+                    .map(|dev| {
+                        (
+                            dev.model,
+                            &dev.serial as &str,
+                            dev.firmware_version.as_deref().unwrap_or_default(),
+                            dev.device_type.to_string(),
+                        )
+                    })
                     .collect();
                 Ok(vec![m.msg.method_return().append1(devices_info)])
             })
-            .outarg::<&[(&str, &str)], _>("devices"),
+            .outarg::<&[(&str, &str, &str, &str)], _>("devices"),
+        )
+        .add_m(
+            f.method("get_state", (), move |m| {
+                let manager = m.path.get_data();
+                let states = manager.get_state();
+                let states: Vec<(&str, &str, &str)> = states
+                    .iter()
+                    .map(|state| (state.model, &state.serial as &str, &state.effect as &str))
+                    .collect();
+                Ok(vec![m.msg.method_return().append1(states)])
+            })
+            .outarg::<&[(&str, &str, &str)], _>("states"),
         )
         .add_m(
             f.method("color_sector", (), move |m| {
                 let manager = m.path.get_data();
                 let (color, sector): (&str, u8) = m.msg.read2()?;
-                let rgb =
-                    RgbColor::from_hex(color).map_err(|_err| MethodErr::invalid_arg("color"))?;
+                let cmd = parse_color(color, Some(sector))?;
 
                 info!("Color sector {} with {}", sector, color);
-                manager.send_command(ColorSector(rgb, Some(sector)));
+                manager.send_command(cmd).map_err(|err| command_err(&err))?;
 
                 Ok(vec![m.msg.method_return()])
             })
             .inarg::<&str, _>("color")
             .inarg::<u8, _>("sector"),
         )
+        .add_m(
+            f.method("color_fade", (), move |m| {
+                let manager = m.path.get_data();
+                let (color, sector, duration): (&str, u8, u32) = m.msg.read3()?;
+                let cmd = parse_color(color, Some(sector))?;
+
+                info!("Fade sector {} to {} in {} ms", sector, color, duration);
+                manager
+                    .send_command_smooth(cmd, Duration::from_millis(duration.into()))
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("color")
+            .inarg::<u8, _>("sector")
+            .inarg::<u32, _>("duration_ms"),
+        )
+        .add_m(
+            f.method("color_sector_on", (), move |m| {
+                let manager = m.path.get_data();
+                let (serial, color, sector): (&str, &str, u8) = m.msg.read3()?;
+                let cmd = parse_color(color, Some(sector))?;
+
+                info!("Color sector {} of {} with {}", sector, serial, color);
+                manager
+                    .send_command_to(serial, cmd)
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("serial")
+            .inarg::<&str, _>("color")
+            .inarg::<u8, _>("sector"),
+        )
         .add_m(
             f.method("color_sectors", (), move |m| {
                 let manager = m.path.get_data();
                 let color: &str = m.msg.read1()?;
-                let rgb =
-                    RgbColor::from_hex(color).map_err(|_err| MethodErr::invalid_arg("color"))?;
+                let cmd = parse_color(color, None)?;
 
                 info!("Color sectors with {}", color);
-                manager.send_command(ColorSector(rgb, None));
+                manager.send_command(cmd).map_err(|err| command_err(&err))?;
 
                 Ok(vec![m.msg.method_return()])
             })
             .inarg::<&str, _>("color"),
         )
+        .add_m(
+            f.method("color_all_sectors", (), move |m| {
+                let manager = m.path.get_data();
+                let colors: Vec<&str> = m.msg.read1()?;
+                let colors = colors
+                    .iter()
+                    .map(|color| RgbColor::parse(color))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_err| MethodErr::invalid_arg("colors"))?;
+
+                info!("Color all sectors with {:?}", colors);
+                manager
+                    .send_command(Command::ColorSectors(colors))
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&[&str], _>("colors"),
+        )
+        .add_m(
+            f.method("triple_color", (), move |m| {
+                let manager = m.path.get_data();
+                let (left, middle, right): (&str, &str, &str) = m.msg.read3()?;
+                let parse = |color: &str, arg: &'static str| {
+                    RgbColor::parse(color).map_err(|_err| MethodErr::invalid_arg(arg))
+                };
+                let cmd = Command::TripleColor(
+                    parse(left, "left")?,
+                    parse(middle, "middle")?,
+                    parse(right, "right")?,
+                );
+
+                info!("Set triple color {} {} {}", left, middle, right);
+                manager.send_command(cmd).map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("left")
+            .inarg::<&str, _>("middle")
+            .inarg::<&str, _>("right"),
+        )
+        .add_m(
+            f.method("gradient", (), move |m| {
+                let manager = m.path.get_data();
+                let (start, end): (&str, &str) = m.msg.read2()?;
+                let cmd = Command::Gradient(
+                    RgbColor::parse(start).map_err(|_err| MethodErr::invalid_arg("start"))?,
+                    RgbColor::parse(end).map_err(|_err| MethodErr::invalid_arg("end"))?,
+                );
+
+                info!("Set gradient from {} to {}", start, end);
+                manager.send_command(cmd).map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("start")
+            .inarg::<&str, _>("end"),
+        )
+        .add_m(
+            f.method("send", (), move |m| {
+                let manager = m.path.get_data();
+                let (kind, params): (&str, HashMap<String, String>) = m.msg.read2()?;
+
+                info!("Send {} command: {:?}", kind, params);
+                let mut props = Properties::new();
+                props.insert("type", kind);
+                for (key, value) in params {
+                    props.insert(key, value);
+                }
+                manager
+                    .send_properties(&props)
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("type")
+            .inarg::<HashMap<&str, &str>, _>("params"),
+        )
+        .add_m(
+            f.method("send_to", (), move |m| {
+                let manager = m.path.get_data();
+                let (serial, kind, params): (&str, &str, HashMap<String, String>) =
+                    m.msg.read3()?;
+
+                info!("Send {} command to {}: {:?}", kind, serial, params);
+                let mut props = Properties::new();
+                props.insert("type", kind);
+                for (key, value) in params {
+                    props.insert(key, value);
+                }
+                manager
+                    .send_properties_to(serial, &props)
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("serial")
+            .inarg::<&str, _>("type")
+            .inarg::<HashMap<&str, &str>, _>("params"),
+        )
+        .add_m(
+            f.method("apply", (), move |m| {
+                let manager = m.path.get_data();
+                let params: HashMap<String, String> = m.msg.read1()?;
+
+                info!("Apply config section: {:?}", params);
+                let mut props = Properties::new();
+                for (key, value) in params {
+                    props.insert(key, value);
+                }
+                manager
+                    .apply_properties(&props)
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<HashMap<&str, &str>, _>("section"),
+        )
+        .add_m(
+            f.method("push_frame", (), move |m| {
+                let manager = m.path.get_data();
+                let colors: Vec<&str> = m.msg.read1()?;
+                let rgbs = colors
+                    .iter()
+                    .map(|color| RgbColor::parse(color))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_err| MethodErr::invalid_arg("colors"))?;
+
+                debug!("Push frame {:?}", colors);
+                manager.push_frame(&rgbs).map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&[&str], _>("colors"),
+        )
         .add_m(
             f.method("breathe", (), move |m| {
                 let manager = m.path.get_data();
                 let (color, speed, brightness): (&str, u16, u8) = m.msg.read3()?;
-                let rgb =
-                    RgbColor::from_hex(color).map_err(|_err| MethodErr::invalid_arg("color"))?;
+                let rgb = RgbColor::parse(color).map_err(|_err| MethodErr::invalid_arg("color"))?;
 
                 info!(
                     "Set breathe mode: color={} speed={} brightness={}",
                     color, speed, brightness
                 );
-                manager.send_command(Breathe(
-                    rgb,
-                    Some(speed.into()),
-                    parse_brightness(brightness)?,
-                ));
+                manager
+                    .send_command(Breathe(
+                        rgb,
+                        Some(parse_speed(speed)?),
+                        parse_brightness(brightness)?,
+                    ))
+                    .map_err(|err| command_err(&err))?;
 
                 Ok(vec![m.msg.method_return()])
             })
@@ -119,19 +376,72 @@ fn create_interface() -> Interface<MTSync<TreeData>, TreeData> {
             .inarg::<u16, _>("speed")
             .inarg::<u8, _>("brightness"),
         )
+        .add_m(
+            f.method("reactive", (), move |m| {
+                let manager = m.path.get_data();
+                let (color, speed): (&str, u16) = m.msg.read2()?;
+                let rgb = RgbColor::parse(color).map_err(|_err| MethodErr::invalid_arg("color"))?;
+
+                info!("Set reactive mode: color={} speed={}", color, speed);
+                manager
+                    .send_command(Command::Reactive(rgb, parse_speed(speed)?))
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("color")
+            .inarg::<u16, _>("speed"),
+        )
         .add_m(
             f.method("cycle", (), move |m| {
                 let manager = m.path.get_data();
                 let (speed, brightness): (u16, u8) = m.msg.read2()?;
 
                 info!("Set cycle mode: speed={} brightness={}", speed, brightness);
-                manager.send_command(Cycle(Some(speed.into()), parse_brightness(brightness)?));
+                manager
+                    .send_command(Cycle(
+                        Some(parse_speed(speed)?),
+                        parse_brightness(brightness)?,
+                    ))
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<u16, _>("speed")
+            .inarg::<u8, _>("brightness"),
+        )
+        .add_m(
+            f.method("blend", (), move |m| {
+                let manager = m.path.get_data();
+                let (speed, brightness): (u16, u8) = m.msg.read2()?;
+
+                info!("Set blend mode: speed={} brightness={}", speed, brightness);
+                manager
+                    .send_command(Command::Blend(
+                        Some(parse_speed(speed)?),
+                        parse_brightness(brightness)?,
+                    ))
+                    .map_err(|err| command_err(&err))?;
 
                 Ok(vec![m.msg.method_return()])
             })
             .inarg::<u16, _>("speed")
             .inarg::<u8, _>("brightness"),
         )
+        .add_m(
+            f.method("start_effect", (), move |m| {
+                let manager = m.path.get_data();
+                let state: bool = m.msg.read1()?;
+
+                info!("Set start effect: {}", state);
+                manager
+                    .send_command(Command::StartEffect(state))
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<bool, _>("state"),
+        )
         .add_m(
             f.method("wave", (), move |m| {
                 let manager = m.path.get_data();
@@ -141,13 +451,15 @@ fn create_interface() -> Interface<MTSync<TreeData>, TreeData> {
                     "Set wave: speed={} direction={:?} brightness={}",
                     speed, direction, brightness
                 );
-                manager.send_command(Wave(
-                    direction
-                        .try_into()
-                        .map_err(|_err| MethodErr::invalid_arg("direction"))?,
-                    Some(speed.into()),
-                    parse_brightness(brightness)?,
-                ));
+                manager
+                    .send_command(Wave(
+                        direction
+                            .try_into()
+                            .map_err(|_err| MethodErr::invalid_arg("direction"))?,
+                        Some(parse_speed(speed)?),
+                        parse_brightness(brightness)?,
+                    ))
+                    .map_err(|err| command_err(&err))?;
 
                 Ok(vec![m.msg.method_return()])
             })
@@ -155,6 +467,246 @@ fn create_interface() -> Interface<MTSync<TreeData>, TreeData> {
             .inarg::<u16, _>("speed")
             .inarg::<u8, _>("brightness"),
         )
+        .add_m(
+            f.method("set_global_brightness_override", (), move |m| {
+                let manager = m.path.get_data();
+                let brightness: u8 = m.msg.read1()?;
+
+                info!("Override brightness of all devices: {}", brightness);
+                manager.set_brightness_override(parse_brightness(brightness)?);
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<u8, _>("brightness"),
+        )
+        .add_m(f.method("off", (), move |m| {
+            let manager = m.path.get_data();
+
+            info!("Turn lighting off");
+            manager
+                .send_command(Command::Off)
+                .map_err(|err| command_err(&err))?;
+
+            Ok(vec![m.msg.method_return()])
+        }))
+        .add_m(
+            f.method("software_rainbow", (), move |m| {
+                let manager = m.path.get_data();
+                let speed: u16 = m.msg.read1()?;
+
+                info!("Start software rainbow with speed {}", speed);
+                manager.start_software_rainbow(speed.into());
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<u16, _>("speed"),
+        )
+        .add_m(f.method("stop", (), move |m| {
+            let manager = m.path.get_data();
+
+            info!("Stop software effect");
+            manager.stop_software_effect();
+
+            Ok(vec![m.msg.method_return()])
+        }))
+        .add_m(f.method("test_pattern", (), move |m| {
+            let manager = m.path.get_data();
+
+            info!("Show test pattern");
+            manager
+                .send_command(Command::TestPattern)
+                .map_err(|err| command_err(&err))?;
+
+            Ok(vec![m.msg.method_return()])
+        }))
+        .add_m(f.method("clear_global_brightness_override", (), move |m| {
+            let manager = m.path.get_data();
+
+            info!("Clear brightness override");
+            manager.set_brightness_override(None);
+
+            Ok(vec![m.msg.method_return()])
+        }))
+        .add_m(
+            f.method("select_onboard_profile", (), move |m| {
+                let manager = m.path.get_data();
+                let slot: u8 = m.msg.read1()?;
+
+                info!("Select onboard profile {}", slot);
+                manager
+                    .send_command(SelectOnboardProfile(slot))
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<u8, _>("slot"),
+        )
+        .add_m(
+            f.method("set_brightness", (), move |m| {
+                let manager = m.path.get_data();
+                let brightness: u8 = m.msg.read1()?;
+
+                info!("Set brightness to {}", brightness);
+                let brightness =
+                    Brightness::try_from(brightness).map_err(|err| MethodErr::invalid_arg(&err))?;
+                manager
+                    .send_command(Command::SetBrightness(brightness))
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<u8, _>("brightness"),
+        )
+        .add_m(
+            f.method("set_brightness_on", (), move |m| {
+                let manager = m.path.get_data();
+                let (serial, brightness): (&str, u8) = m.msg.read2()?;
+
+                info!("Set brightness of {} to {}", serial, brightness);
+                let brightness =
+                    Brightness::try_from(brightness).map_err(|err| MethodErr::invalid_arg(&err))?;
+                manager
+                    .send_command_to(serial, Command::SetBrightness(brightness))
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("serial")
+            .inarg::<u8, _>("brightness"),
+        )
+        .add_m(
+            f.method("dpi", (), move |m| {
+                let manager = m.path.get_data();
+                let dpi: u16 = m.msg.read1()?;
+
+                info!("Set DPI to {}", dpi);
+                manager
+                    .send_command(Command::Dpi(dpi.into()))
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<u16, _>("dpi"),
+        )
+        .add_m(
+            f.method("dpi_on", (), move |m| {
+                let manager = m.path.get_data();
+                let (serial, dpi): (&str, u16) = m.msg.read2()?;
+
+                info!("Set DPI of {} to {}", serial, dpi);
+                manager
+                    .send_command_to(serial, Command::Dpi(dpi.into()))
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("serial")
+            .inarg::<u16, _>("dpi"),
+        )
+        .add_m(
+            f.method("set_feature_report", (), move |m| {
+                let manager = m.path.get_data();
+                let (feature, params): (u8, Vec<u8>) = m.msg.read2()?;
+
+                info!("Send raw feature report {:#04x} {:02x?}", feature, params);
+                manager
+                    .send_command(SetFeatureReport(feature, params))
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<u8, _>("feature")
+            .inarg::<&[u8], _>("params"),
+        )
+        .add_m(
+            f.method("get_supported_effects", (), move |m| {
+                let manager = m.path.get_data();
+                let from_device: bool = m.msg.read1()?;
+                let effects: Vec<(String, Vec<String>)> = manager
+                    .get_supported_effects(from_device)
+                    .into_iter()
+                    .map(|(serial, effects)| {
+                        (serial, effects.iter().map(|e| e.to_string()).collect())
+                    })
+                    .collect();
+                Ok(vec![m.msg.method_return().append1(effects)])
+            })
+            .inarg::<bool, _>("from_device")
+            .outarg::<&[(&str, &[&str])], _>("effects"),
+        )
+        .add_m(
+            f.method("get_capabilities", (), move |m| {
+                let manager = m.path.get_data();
+                let capabilities = manager.get_capabilities();
+                let capabilities: Vec<(&str, &str, u8, &[String])> = capabilities
+                    .iter()
+                    .map(|dev| {
+                        (
+                            dev.model,
+                            &dev.serial as &str,
+                            dev.sectors,
+                            &dev.features[..],
+                        )
+                    })
+                    .collect();
+                Ok(vec![m.msg.method_return().append1(capabilities)])
+            })
+            .outarg::<&[(&str, &str, u8, &[&str])], _>("capabilities"),
+        )
+        .add_m(
+            f.method("get_onboard_profiles", (), move |m| {
+                let manager = m.path.get_data();
+                let profiles = manager.get_onboard_profiles();
+                Ok(vec![m.msg.method_return().append1(profiles)])
+            })
+            .outarg::<&[(&str, u8)], _>("profiles"),
+        )
+        .add_m(
+            f.method("probe", (), move |m| {
+                let manager = m.path.get_data();
+                let serial: &str = m.msg.read1()?;
+
+                info!("Probe features of {}", serial);
+                let features = manager
+                    .probe_features(serial)
+                    .map_err(|err| command_err(&err))?;
+                Ok(vec![m.msg.method_return().append1(features)])
+            })
+            .inarg::<&str, _>("serial")
+            .outarg::<&[(u8, u16)], _>("features"),
+        )
+        .add_m(
+            f.method("get_dpi_stages", (), move |m| {
+                let manager = m.path.get_data();
+                let stages = manager.get_dpi_stages();
+                Ok(vec![m.msg.method_return().append1(stages)])
+            })
+            .outarg::<&[(&str, u8)], _>("stages"),
+        )
+        .add_m(
+            f.method("get_debug_info", (), move |m| {
+                let manager = m.path.get_data();
+                let info = manager.get_debug_info();
+                Ok(vec![m.msg.method_return().append1(info)])
+            })
+            .outarg::<&[(&str, &str)], _>("info"),
+        )
+        .add_m(
+            f.method("config_warnings", (), move |m| {
+                let manager = m.path.get_data();
+                let warnings = manager.config_warnings();
+                Ok(vec![m.msg.method_return().append1(warnings)])
+            })
+            .outarg::<&[&str], _>("warnings"),
+        )
+        .add_m(f.method("save", (), move |m| {
+            let manager = m.path.get_data();
+
+            info!("Save current lighting");
+            manager.save();
+
+            Ok(vec![m.msg.method_return()])
+        }))
         .add_m(f.method("refresh", (), move |m| {
             let manager = m.path.get_data();
 
@@ -163,9 +715,83 @@ fn create_interface() -> Interface<MTSync<TreeData>, TreeData> {
 
             Ok(vec![m.msg.method_return()])
         }))
+        .add_m(
+            f.method("apply_profile", (), move |m| {
+                let manager = m.path.get_data();
+                let name: &str = m.msg.read1()?;
+
+                info!("Apply profile {}", name);
+                manager.apply_profile(name);
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("name"),
+        )
+        .add_m(
+            f.method("toggle_lighting", (), move |m| {
+                let manager = m.path.get_data();
+                let serial: &str = m.msg.read1()?;
+
+                info!("Toggle lighting of device {}", serial);
+                let on = manager
+                    .toggle_lighting(serial)
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return().append1(on)])
+            })
+            .inarg::<&str, _>("serial")
+            .outarg::<bool, _>("on"),
+        )
+        .add_m(
+            f.method("lock", (), move |m| {
+                let manager = m.path.get_data();
+                let serial: &str = m.msg.read1()?;
+
+                info!("Lock device {}", serial);
+                manager
+                    .set_locked(serial, true)
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("serial"),
+        )
+        .add_m(
+            f.method("unlock", (), move |m| {
+                let manager = m.path.get_data();
+                let serial: &str = m.msg.read1()?;
+
+                info!("Unlock device {}", serial);
+                manager
+                    .set_locked(serial, false)
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("serial"),
+        )
+        .add_m(
+            f.method("refresh_device", (), move |m| {
+                let manager = m.path.get_data();
+                let serial: &str = m.msg.read1()?;
+
+                info!("Refresh device {}", serial);
+                manager
+                    .apply_config_to(serial)
+                    .map_err(|err| command_err(&err))?;
+
+                Ok(vec![m.msg.method_return()])
+            })
+            .inarg::<&str, _>("serial"),
+        )
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    if args.oneshot {
+        return run_oneshot(&args);
+    }
+
     let term_now = register_forced_shutdown()?;
     let mut signals = Signals::new(TERM_SIGNALS)?;
     let sigs_handle = signals.handle();
@@ -173,47 +799,61 @@ fn main() -> Result<(), Box<dyn Error>> {
     simple_logger::init_with_env()?;
 
     // Register DBus service
-    let c = Connection::new_system()?;
+    let c = if args.session {
+        Connection::new_session()?
+    } else {
+        Connection::new_system()?
+    };
     c.request_name("de.richardliebscher.gdevd", false, false, true)?;
 
     // Start USB service
-    let device_manager = Arc::new(GDeviceManager::try_new()?);
+    let device_manager = Arc::new(GDeviceManager::try_with_config(args.load_config())?);
     device_manager.load_devices()?;
 
     let gdevmgr = device_manager.clone();
-    let usb_context = device_manager.context();
     let term_now_ = term_now.clone();
-    let events_thd = thread::spawn(move || {
-        while !term_now_.load(Ordering::Relaxed) {
-            if let Err(err) = usb_context.handle_events(None) {
-                error!("libusb event handling aborted: {err}");
-                let _ = gdevmgr.channel().send(GDeviceManagerEvent::Shutdown);
-                return;
+    let events_thd = device_manager.context().map(|usb_context| {
+        thread::spawn(move || {
+            while !term_now_.load(Ordering::Relaxed) {
+                if let Err(err) = usb_context.handle_events(None) {
+                    error!("libusb event handling aborted: {err}");
+                    let _ = gdevmgr.channel().send(GDeviceManagerEvent::Shutdown);
+                    return;
+                }
             }
-        }
+        })
     });
 
     // DBus
     let devmgr = device_manager.clone();
     let term_now_ = term_now.clone();
+    let device_changes = device_manager.watch_devices();
     let dbus_thd = thread::spawn(move || {
-        let device_manager_if = create_interface();
         let f = Factory::new_sync::<TreeData>();
+        let device_list_changed = Arc::new(f.signal("DeviceListChanged", ()));
+        let device_manager_if = create_interface(device_list_changed.clone());
         let tree = f.tree(()).add(
-            f.object_path("/devices", devmgr.clone())
+            f.object_path(OBJECT_PATH, devmgr.clone())
                 .introspectable()
                 .add(device_manager_if),
         );
 
         tree.start_receive_send(&c);
+        watch_resume(&c, &devmgr);
 
         info!("Starting DBus server");
         while !term_now_.load(Ordering::Relaxed) {
-            if let Err(err) = c.process(Duration::from_millis(2000)) {
+            if let Err(err) = c.process(Duration::from_millis(500)) {
                 error!("DBus server aborted: {err}");
                 let _ = devmgr.channel().send(GDeviceManagerEvent::Shutdown);
                 return;
             }
+            if device_changes.try_iter().count() > 0 {
+                let msg = device_list_changed.msg(&OBJECT_PATH.into(), &INTERFACE_NAME.into());
+                if c.channel().send(msg).is_err() {
+                    warn!("Failed to emit DeviceListChanged signal");
+                }
+            }
         }
     });
 
@@ -231,17 +871,45 @@ fn main() -> Result<(), Box<dyn Error>> {
     info!("Terminating...");
     // Interrupt threads
     term_now.store(true, Ordering::Release);
-    device_manager.context().interrupt_handle_events();
+    if let Some(usb_context) = device_manager.context() {
+        usb_context.interrupt_handle_events();
+    }
     sigs_handle.close();
 
     // Wait till the end
     dbus_thd.join().expect("DBus thread panicked");
-    events_thd.join().expect("USB thread panicked");
+    if let Some(events_thd) = events_thd {
+        events_thd.join().expect("USB thread panicked");
+    }
     sigs_thd.join().expect("Signal thread panicked");
 
     Ok(())
 }
 
+/// Send `Resumed` event when systemd-logind reports wake-up from suspend
+fn watch_resume(c: &Connection, device_manager: &GDeviceManager) {
+    let channel = device_manager.channel().clone();
+    let rule = MatchRule::new_signal("org.freedesktop.login1.Manager", "PrepareForSleep");
+    let result = c.add_match(rule, move |(sleeping,): (bool,), _, _| {
+        if !sleeping {
+            let _ = channel.send(GDeviceManagerEvent::Resumed);
+        }
+        true
+    });
+    if let Err(err) = result {
+        warn!("Config is not reapplied after suspend: {err}");
+    }
+}
+
+fn run_oneshot(args: &Args) -> Result<(), Box<dyn Error>> {
+    simple_logger::init_with_env()?;
+
+    let device_manager = GDeviceManager::try_with_config(args.load_config())?;
+    device_manager.scan_devices()?;
+    device_manager.apply_config();
+    Ok(())
+}
+
 fn register_forced_shutdown() -> Result<Arc<AtomicBool>, Box<dyn Error>> {
     // Make sure double CTRL+C and similar kills
     let term_now = Arc::new(AtomicBool::new(false));
@@ -251,3 +919,104 @@ fn register_forced_shutdown() -> Result<Arc<AtomicBool>, Box<dyn Error>> {
     }
     Ok(term_now)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use dbus::message::MessageType;
+    use dbus::Message;
+    use dbus_tree::Tree;
+
+    use gdevd::drivers::CommandSink;
+
+    use super::*;
+
+    /// Sink recording reports, clones share the recorded reports
+    #[derive(Clone, Default)]
+    struct RecordingSink {
+        reports: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl RecordingSink {
+        fn reports(&self) -> Vec<Vec<u8>> {
+            self.reports.lock().unwrap().clone()
+        }
+    }
+
+    impl CommandSink for RecordingSink {
+        fn send(&self, data: &[u8]) -> Result<(), CommandError> {
+            self.reports.lock().unwrap().push(data.to_vec());
+            Ok(())
+        }
+    }
+
+    /// Interface of manager that records reports of every allowed driver
+    fn tree(sink: &RecordingSink) -> Tree<MTSync<TreeData>, TreeData> {
+        let config = Config::load_from(Path::new("/nonexistent/gdevd.conf"));
+        let manager = Arc::new(GDeviceManager::try_with_sink(config, sink.clone()).unwrap());
+        let f = Factory::new_sync::<TreeData>();
+        let device_list_changed = Arc::new(f.signal("DeviceListChanged", ()));
+        f.tree(()).add(
+            f.object_path(OBJECT_PATH, manager)
+                .add(create_interface(device_list_changed)),
+        )
+    }
+
+    fn method_call(method: &str) -> Message {
+        let mut msg = Message::new_method_call(
+            "de.richardliebscher.gdevd",
+            OBJECT_PATH,
+            INTERFACE_NAME,
+            method,
+        )
+        .unwrap();
+        msg.set_serial(1);
+        msg
+    }
+
+    fn handle(tree: &Tree<MTSync<TreeData>, TreeData>, msg: Message) -> Message {
+        tree.handle(&msg).unwrap().remove(0)
+    }
+
+    #[test]
+    fn color_sector_sends_region_color() {
+        let sink = RecordingSink::default();
+        let reply = handle(
+            &tree(&sink),
+            method_call("color_sector").append2("ff8000", 1u8),
+        );
+
+        assert_eq!(reply.msg_type(), MessageType::MethodReturn);
+        let mut g213_report = vec![0x11, 0xff, 0x0c, 0x3a, 0x02, 0x01, 0xff, 0x80, 0x00, 0x02];
+        g213_report.resize(20, 0);
+        assert!(sink.reports().contains(&g213_report));
+    }
+
+    #[test]
+    fn color_sector_rejects_invalid_color() {
+        let sink = RecordingSink::default();
+        let mut reply = handle(
+            &tree(&sink),
+            method_call("color_sector").append2("nocolor", 1u8),
+        );
+
+        let err = reply.as_result().unwrap_err();
+        assert_eq!(err.name(), Some("org.freedesktop.DBus.Error.InvalidArgs"));
+        assert!(sink.reports().is_empty());
+    }
+
+    #[test]
+    fn breathe_rejects_too_short_period() {
+        let sink = RecordingSink::default();
+        let mut reply = handle(
+            &tree(&sink),
+            method_call("breathe").append3("ff8000", MIN_SPEED_MILLIS - 1, 100u8),
+        );
+
+        let err = reply.as_result().unwrap_err();
+        assert_eq!(err.name(), Some("org.freedesktop.DBus.Error.InvalidArgs"));
+        assert!(sink.reports().is_empty());
+    }
+}
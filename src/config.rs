@@ -1,51 +1,456 @@
+use std::cell::RefCell;
 use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{env, io};
 
 use ini::{Ini, Properties, SectionSetter};
 
-use crate::{Brightness, Command, Direction, GDeviceModel, RgbColor, Speed};
+use crate::{
+    Brightness, Command, Direction, Dpi, GDeviceModel, GDeviceModelRef, RgbColor, RgbwColor, Speed,
+};
 
 const CONFIG_PATH: &str = "/etc/gdevd.conf";
+const CONFIG_ENV: &str = "GDEVD_CONFIG";
+const DAEMON_SECTION: &str = "gdevd";
+const DAEMON_KEYS: &[&str] = &[
+    "allow_experimental",
+    "allow_raw",
+    "apply_on_start",
+    "profile",
+    "refresh_interval_secs",
+];
+const DEVICE_KEYS: &[&str] = &[
+    "type",
+    "color",
+    "colors",
+    "speed",
+    "brightness",
+    "direction",
+    "state",
+    "start_effect",
+    "profile",
+    "onboard-profile",
+    "dpi",
+    "lock",
+    "off",
+    "enabled",
+    "gamma",
+    "intensity",
+];
+const COMMAND_TYPES: &[&str] = &[
+    "static",
+    "static-all",
+    "triple",
+    "breathe",
+    "breath",
+    "cycle",
+    "wave",
+    "blend",
+    "reactive",
+    "startEffect",
+    "onboardProfile",
+    "off",
+];
 
-pub struct Config(Ini);
+pub struct Config {
+    ini: Ini,
+    path: PathBuf,
+    warnings: RefCell<Vec<String>>,
+}
 
 impl Config {
+    /// Load config from path in `GDEVD_CONFIG` environment variable or default path
     pub fn load() -> Self {
-        let ini = Ini::load_from_file(CONFIG_PATH).unwrap_or_else(|err| {
+        match env::var_os(CONFIG_ENV) {
+            Some(path) => Self::load_from(Path::new(&path)),
+            None => Self::load_from(Path::new(CONFIG_PATH)),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        let ini = Ini::load_from_file(path).unwrap_or_else(|err| {
             warn!(
                 "Config file {} has invalid format and is ignored: {:?}",
-                CONFIG_PATH, err
+                path.display(),
+                err
             );
             Ini::new()
         });
 
-        Self(ini)
+        Self {
+            ini,
+            path: path.to_path_buf(),
+            warnings: RefCell::new(vec![]),
+        }
     }
 
-    pub fn commands_for(&self, model: &dyn GDeviceModel) -> Vec<Command> {
-        let model_name = model.get_name();
-        self.0
-            .section(Some(model_name))
+    /// Config from string that is never written to disk
+    #[cfg(test)]
+    pub(crate) fn from_str(ini: &str) -> Self {
+        Self {
+            ini: Ini::load_from_str(ini).unwrap(),
+            path: PathBuf::new(),
+            warnings: RefCell::new(vec![]),
+        }
+    }
+
+    /// Log warning about ignored or invalid config entry and remember it
+    fn warn(&self, message: String) {
+        warn!("{}", message);
+        let mut warnings = self.warnings.borrow_mut();
+        if !warnings.contains(&message) {
+            warnings.push(message);
+        }
+    }
+
+    /// Check complete config and return all warnings
+    ///
+    /// Every section is parsed for the model it belongs to, so warnings are reported even
+    /// for models without a connected device.
+    pub fn check(&self, models: &[GDeviceModelRef]) -> Vec<String> {
+        self.warnings.borrow_mut().clear();
+
+        for (section, props) in self.ini.iter() {
+            let section = match section {
+                Some(section) => section,
+                None if props.is_empty() => continue,
+                None => {
+                    self.warn("Keys outside of section ignored".to_string());
+                    continue;
+                }
+            };
+
+            if section == DAEMON_SECTION {
+                for (key, _) in props.iter() {
+                    if key == "profile" {
+                        continue;
+                    } else if key == "refresh_interval_secs" {
+                        self.refresh_interval();
+                    } else if DAEMON_KEYS.contains(&key) {
+                        self.daemon_bool(key);
+                    } else {
+                        self.warn(format!("Unknown key {}.{} ignored", section, key));
+                    }
+                }
+                continue;
+            }
+
+            let model_name = section.split(['@', ':']).next().unwrap_or_default();
+            let model = match models.iter().find(|model| model.get_name() == model_name) {
+                Some(model) => model,
+                None => {
+                    self.warn(format!("Unknown device section [{}] ignored", section));
+                    continue;
+                }
+            };
+
+            for (key, _) in props.iter() {
+                if !Self::is_device_key(key) {
+                    self.warn(format!("Unknown key {}.{} ignored", section, key));
+                }
+            }
+            self.parse_model_config(props, &**model);
+            self.parse_bool(props, &**model, "lock");
+            self.parse_bool(props, &**model, "off");
+            self.parse_gamma(props, &**model);
+            self.parse_intensity(props, &**model);
+            if let Some((_, serial)) = section.split_once('@') {
+                self.check_overlap(&**model, serial);
+            } else if !section.contains(':') {
+                self.check_overlap(&**model, "");
+            }
+        }
+
+        self.warnings.borrow().clone()
+    }
+
+    fn is_device_key(key: &str) -> bool {
+        DEVICE_KEYS.contains(&key)
+            || key
+                .strip_prefix("color-")
+                .is_some_and(|sector| sector.parse::<u8>().is_ok())
+    }
+
+    /// Path of config file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return whether experimental drivers may claim devices
+    pub fn allow_experimental(&self) -> bool {
+        self.daemon_bool("allow_experimental")
+            .unwrap_or(cfg!(feature = "experimental"))
+    }
+
+    /// Return whether raw HID++ feature reports may be sent to devices
+    pub fn allow_raw(&self) -> bool {
+        self.daemon_bool("allow_raw").unwrap_or(false)
+    }
+
+    /// Return whether config should be sent to devices on daemon start
+    pub fn apply_on_start(&self) -> bool {
+        self.daemon_bool("apply_on_start").unwrap_or(true)
+    }
+
+    /// Interval for resending config to devices that forget their lighting
+    ///
+    /// `None` if periodic refresh is disabled, which is the default.
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        let secs = self
+            .ini
+            .get_from(Some(DAEMON_SECTION), "refresh_interval_secs")?;
+        match secs.parse::<u64>() {
+            Ok(secs) if secs > 0 => Some(Duration::from_secs(secs)),
+            _ => {
+                self.warn(format!(
+                    "Invalid interval {} for {}.refresh_interval_secs ignored",
+                    secs, DAEMON_SECTION
+                ));
+                None
+            }
+        }
+    }
+
+    /// Name of active profile
+    pub fn active_profile(&self) -> Option<&str> {
+        self.ini.get_from(Some(DAEMON_SECTION), "profile")
+    }
+
+    /// Return whether any device section exists for profile (`[<model>:<profile>]`)
+    pub fn has_profile(&self, profile: &str) -> bool {
+        self.ini.sections().flatten().any(|section| {
+            section
+                .split_once(':')
+                .is_some_and(|(_, name)| name == profile)
+        })
+    }
+
+    /// Make profile active and save it
+    pub fn set_active_profile(&mut self, profile: &str) {
+        self.ini
+            .with_section(Some(DAEMON_SECTION))
+            .set("profile", profile);
+        self.save();
+    }
+
+    fn daemon_bool(&self, key: &str) -> Option<bool> {
+        if let Some(boolean) = self.ini.get_from(Some(DAEMON_SECTION), key) {
+            if let Ok(boolean) = boolean.parse::<bool>() {
+                return Some(boolean);
+            } else {
+                self.warn(format!(
+                    "Invalid boolean {} for {}.{} ignored",
+                    boolean, DAEMON_SECTION, key
+                ));
+            }
+        }
+
+        None
+    }
+
+    /// Return whether device is locked against changes (`lock=true`)
+    pub fn is_locked(&self, model: &dyn GDeviceModel, serial: &str) -> bool {
+        self.ini
+            .section(Some(self.section_name_for(model, serial)))
+            .map(|props| self.parse_bool(props, model, "lock").unwrap_or(false))
+            .unwrap_or(false)
+    }
+
+    pub fn set_locked(&mut self, model: &dyn GDeviceModel, serial: &str, locked: bool) {
+        let section_name = self.section_name_for(model, serial);
+        self.ini
+            .with_section(Some(section_name))
+            .set("lock", if locked { "true" } else { "false" });
+        self.save();
+    }
+
+    /// Return whether device is managed by daemon (`enabled=false` excludes it)
+    pub fn is_enabled(&self, model: &dyn GDeviceModel, serial: &str) -> bool {
+        self.ini
+            .section(Some(self.section_name_for(model, serial)))
+            .map(|props| self.parse_bool(props, model, "enabled").unwrap_or(true))
+            .unwrap_or(true)
+    }
+
+    /// Return whether lighting of device was toggled off
+    pub fn is_off(&self, model: &dyn GDeviceModel, serial: &str) -> bool {
+        self.ini
+            .section(Some(self.section_name_for(model, serial)))
+            .map(|props| self.parse_bool(props, model, "off").unwrap_or(false))
+            .unwrap_or(false)
+    }
+
+    /// Gamma correction for colors of device if configured
+    pub fn gamma(&self, model: &dyn GDeviceModel, serial: &str) -> Option<f32> {
+        self.ini
+            .section(Some(self.section_name_for(model, serial)))
+            .and_then(|props| self.parse_gamma(props, model))
+    }
+
+    /// Describe saved effect of device by the keys of its config section
+    pub fn describe(&self, model: &dyn GDeviceModel, serial: &str) -> String {
+        if !self.is_enabled(model, serial) {
+            return "disabled".to_string();
+        }
+        if self.is_off(model, serial) {
+            return "off".to_string();
+        }
+
+        let section_name = self.section_name_for(model, serial);
+        let keys: Vec<String> = self
+            .ini
+            .section(Some(section_name))
+            .map(|props| {
+                props
+                    .iter()
+                    .filter(|(key, _)| !matches!(*key, "lock" | "off" | "enabled"))
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if keys.is_empty() {
+            "default".to_string()
+        } else {
+            keys.join(" ")
+        }
+    }
+
+    pub fn set_off(&mut self, model: &dyn GDeviceModel, serial: &str, off: bool) {
+        let section_name = self.section_name_for(model, serial);
+        self.ini
+            .with_section(Some(section_name))
+            .set("off", if off { "true" } else { "false" });
+        self.save();
+    }
+
+    /// Return config sections for a device in order of precedence
+    ///
+    /// The section for a single device (`[<model>@<serial>]`) takes precedence over the
+    /// section of the active profile (`[<model>:<profile>]`), which takes precedence over the
+    /// section for its model (`[<model>]`). Only the first existing section is applied, so
+    /// a device is never configured from two sections in sequence.
+    fn device_sections(&self, model: &dyn GDeviceModel, serial: &str) -> Vec<String> {
+        let mut sections = vec![
+            format!("{}@{}", model.get_name(), serial),
+            model.get_name().to_string(),
+        ];
+        if let Some(profile) = self.active_profile() {
+            sections.insert(1, format!("{}:{}", model.get_name(), profile));
+        }
+        sections
+    }
+
+    /// Return existing config sections for a device in order of precedence
+    fn matching_sections(&self, model: &dyn GDeviceModel, serial: &str) -> Vec<String> {
+        self.device_sections(model, serial)
+            .into_iter()
+            .filter(|section| self.ini.section(Some(section as &str)).is_some())
+            .collect()
+    }
+
+    /// Return name of the section that configures the device
+    fn section_name_for(&self, model: &dyn GDeviceModel, serial: &str) -> String {
+        self.matching_sections(model, serial)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| model.get_name().to_string())
+    }
+
+    /// Report sections that match the same device, where only the first one is applied
+    ///
+    /// An empty `serial` checks the overlap of the model section with the active profile,
+    /// which is intended and only logged.
+    fn check_overlap(&self, model: &dyn GDeviceModel, serial: &str) {
+        let sections = self.matching_sections(model, serial);
+        if sections.len() < 2 {
+            return;
+        }
+        if sections[0].contains(':') {
+            info!(
+                "Section [{}] of active profile takes precedence over [{}]",
+                sections[0],
+                sections[1..].join("], [")
+            );
+        } else {
+            self.warn(format!(
+                "Config section [{}] overrides [{}]",
+                sections[0],
+                sections[1..].join("], [")
+            ));
+        }
+    }
+
+    pub fn commands_for(&self, model: &dyn GDeviceModel, serial: &str) -> Vec<Command> {
+        self.matching_sections(model, serial)
+            .first()
+            .and_then(|section| self.ini.section(Some(section as &str)))
             .map(|props| self.parse_model_config(props, model))
             .unwrap_or_default()
     }
 
+    /// Return whether `kind` is a known value for the `type` key
+    pub fn is_command_type(kind: &str) -> bool {
+        COMMAND_TYPES.contains(&kind)
+    }
+
+    /// Parse commands from properties of a device section
+    pub fn parse_commands(&self, props: &Properties, model: &dyn GDeviceModel) -> Vec<Command> {
+        self.parse_model_config(props, model)
+    }
+
     fn parse_model_config(&self, props: &Properties, model: &dyn GDeviceModel) -> Vec<Command> {
         let model_name = model.get_name();
 
-        match props.get("type") {
-            Some("static") => (0..model.get_sectors())
-                .map(|i| {
-                    Command::ColorSector(
-                        self.parse_color_prop(props, model, &format!("color-{i}")),
-                        Some(i),
-                    )
-                })
-                .collect(),
-            Some("static-all") => vec![Command::ColorSector(
+        let mode = props
+            .get("type")
+            .or_else(|| Self::infer_type(props, model_name));
+        let mut commands = match mode {
+            Some("static") => {
+                for (key, _) in props.iter() {
+                    let sector = key.strip_prefix("color-").map(|i| i.parse::<u8>());
+                    if let Some(Ok(sector)) = sector {
+                        if sector >= model.get_sectors() {
+                            self.warn(format!(
+                                "{}.{} ignored because {} has only {} sectors",
+                                model_name,
+                                key,
+                                model_name,
+                                model.get_sectors()
+                            ));
+                        }
+                    }
+                }
+
+                // `color-N` keys take precedence over the compact `colors` list
+                let colors = self.parse_color_list(props, model, "colors");
+                (0..model.get_sectors())
+                    .map(|i| {
+                        let key = format!("color-{i}");
+                        match colors.get(i as usize) {
+                            Some(color) if !props.contains_key(&key) => {
+                                Command::ColorSector(color.clone(), Some(i))
+                            }
+                            _ => self.parse_color_command(props, model, &key, Some(i)),
+                        }
+                    })
+                    .collect()
+            }
+            Some("static-all") => {
+                let key = if props.contains_key("color-0") {
+                    "color-0"
+                } else {
+                    "color"
+                };
+                vec![self.parse_color_command(props, model, key, None)]
+            }
+            Some("triple") => vec![Command::TripleColor(
                 self.parse_color_prop(props, model, "color-0"),
-                None,
+                self.parse_color_prop(props, model, "color-1"),
+                self.parse_color_prop(props, model, "color-2"),
             )],
-            Some("breath") => vec![Command::Breathe(
+            // `breath` was written by older versions
+            Some("breathe" | "breath") => vec![Command::Breathe(
                 self.parse_color_prop(props, model, "color"),
                 self.parse_speed(props, model, "speed"),
                 self.parse_brightness(props, model, "brightness"),
@@ -59,15 +464,134 @@ impl Config {
                 self.parse_speed(props, model, "speed"),
                 self.parse_brightness(props, model, "brightness"),
             )],
+            Some("blend") => vec![Command::Blend(
+                self.parse_speed(props, model, "speed"),
+                self.parse_brightness(props, model, "brightness"),
+            )],
+            Some("reactive") => match self.parse_speed(props, model, "speed") {
+                Some(speed) => vec![Command::Reactive(
+                    self.parse_color_prop(props, model, "color"),
+                    speed,
+                )],
+                None => {
+                    self.warn(format!(
+                        "Missing speed for reactive effect of {}",
+                        model_name
+                    ));
+                    vec![]
+                }
+            },
             Some("startEffect") => vec![Command::StartEffect(
                 self.parse_bool(props, model, "state").unwrap_or(true),
             )],
+            Some("off") => vec![Command::Off],
+            // Onboard profiles were stored as effect by older versions
+            Some("onboardProfile") => self
+                .parse_u8(props, model, "profile")
+                .map(Command::SelectOnboardProfile)
+                .into_iter()
+                .collect(),
             Some(unknown) => {
-                warn!("Unknown color mode `{}` for {}", unknown, model_name);
+                self.warn(format!(
+                    "Unknown color mode `{}` for {}",
+                    unknown, model_name
+                ));
                 vec![]
             }
             None => vec![],
+        };
+
+        // Power-up behavior refers to the effect, so it has to be sent after it
+        if let Some(state) = self.parse_bool(props, model, "start_effect") {
+            commands.push(Command::StartEffect(state));
+        }
+        if let Some(dpi) = self.parse_dpi(props, model, "dpi") {
+            commands.push(Command::Dpi(dpi));
+        }
+        if let Some(slot) = self.parse_u8(props, model, "onboard-profile") {
+            commands.push(Command::SelectOnboardProfile(slot));
+        }
+        commands
+    }
+
+    /// Guess color mode of section without `type` key from its color keys
+    fn infer_type(props: &Properties, model_name: &str) -> Option<&'static str> {
+        let mode = if props.contains_key("color-0") || props.contains_key("colors") {
+            "static"
+        } else if props.contains_key("color") {
+            "static-all"
+        } else {
+            return None;
+        };
+
+        info!(
+            "Missing color mode for {} inferred as `{}` from color keys",
+            model_name, mode
+        );
+        Some(mode)
+    }
+
+    /// Parse color of sector with optional white channel
+    fn parse_color_command(
+        &self,
+        props: &Properties,
+        model: &dyn GDeviceModel,
+        key: &str,
+        sector: Option<u8>,
+    ) -> Command {
+        match props.get(key) {
+            Some(color) if color.len() == 8 => {
+                if let Ok(rgbw) = RgbwColor::from_hex(color) {
+                    let rgbw = match self.parse_intensity(props, model) {
+                        Some(intensity) => rgbw.scale(intensity),
+                        None => rgbw,
+                    };
+                    return Command::ColorSectorRgbw(rgbw, sector);
+                }
+            }
+            _ => {}
         }
+
+        Command::ColorSector(self.parse_color_prop(props, model, key), sector)
+    }
+
+    /// Parse comma separated list of colors
+    fn parse_color_list(
+        &self,
+        props: &Properties,
+        model: &dyn GDeviceModel,
+        key: &str,
+    ) -> Vec<RgbColor> {
+        let colors: Vec<RgbColor> = match props.get(key) {
+            Some(colors) => colors
+                .split(',')
+                .map(|color| {
+                    let color = color.trim();
+                    let rgb = RgbColor::from_hex(color).unwrap_or_else(|_err| {
+                        self.warn(format!(
+                            "Invalid RGB hex color {} for {}.{} ignored",
+                            color,
+                            model.get_name(),
+                            key
+                        ));
+                        model.get_default_color()
+                    });
+                    self.apply_intensity(props, model, rgb)
+                })
+                .collect(),
+            None => return vec![],
+        };
+
+        if colors.len() != model.get_sectors() as usize {
+            self.warn(format!(
+                "{} colors for {}.{} do not match {} sectors",
+                colors.len(),
+                model.get_name(),
+                key,
+                model.get_sectors()
+            ));
+        }
+        colors
     }
 
     fn parse_color_prop(
@@ -76,20 +600,34 @@ impl Config {
         model: &dyn GDeviceModel,
         key: &str,
     ) -> RgbColor {
+        let mut rgb = model.get_default_color();
         if let Some(color) = props.get(key) {
-            if let Ok(rgb) = RgbColor::from_hex(color) {
-                return rgb;
+            if let Ok(color) = RgbColor::from_hex(color) {
+                rgb = color;
             } else {
-                warn!(
+                self.warn(format!(
                     "Invalid RGB hex color {} for {}.{} ignored",
                     color,
                     model.get_name(),
                     key
-                );
+                ));
             }
         }
 
-        model.get_default_color()
+        self.apply_intensity(props, model, rgb)
+    }
+
+    /// Scale color by `intensity` of section if configured
+    fn apply_intensity(
+        &self,
+        props: &Properties,
+        model: &dyn GDeviceModel,
+        color: RgbColor,
+    ) -> RgbColor {
+        match self.parse_intensity(props, model) {
+            Some(intensity) => color.scale(intensity),
+            None => color,
+        }
     }
 
     fn parse_speed(
@@ -99,21 +637,34 @@ impl Config {
         key: &str,
     ) -> Option<Speed> {
         if let Some(speed) = props.get(key) {
-            if let Ok(speed) = speed.parse::<u16>() {
+            if let Some(speed) = Self::parse_millis(speed) {
                 return Some(Speed(speed));
             } else {
-                warn!(
+                self.warn(format!(
                     "Invalid speed {} for {}.{} ignored",
                     speed,
                     model.get_name(),
                     key
-                );
+                ));
             }
         }
 
         None
     }
 
+    /// Parse milliseconds from plain number or duration with `ms` or `s` suffix
+    ///
+    /// Conversion to device units happens when the command is sent.
+    fn parse_millis(value: &str) -> Option<u16> {
+        if let Some(millis) = value.strip_suffix("ms") {
+            millis.trim_end().parse::<u16>().ok()
+        } else if let Some(secs) = value.strip_suffix('s') {
+            secs.trim_end().parse::<u16>().ok()?.checked_mul(1000)
+        } else {
+            value.parse::<u16>().ok()
+        }
+    }
+
     fn parse_brightness(
         &self,
         props: &Properties,
@@ -126,12 +677,12 @@ impl Config {
                     return Some(Brightness(brightness));
                 }
             }
-            warn!(
+            self.warn(format!(
                 "Invalid brightness {} for {}.{} ignored",
                 brightness,
                 model.get_name(),
                 key
-            );
+            ));
         }
 
         None
@@ -145,12 +696,12 @@ impl Config {
     ) -> Direction {
         if let Some(direction) = props.get(key) {
             direction.try_into().unwrap_or_else(|_err| {
-                warn!(
+                self.warn(format!(
                     "Invalid direction {} for {}.{} ignored",
                     direction,
                     model.get_name(),
                     key
-                );
+                ));
                 Direction::LeftToRight
             })
         } else {
@@ -158,25 +709,102 @@ impl Config {
         }
     }
 
+    fn parse_dpi(&self, props: &Properties, model: &dyn GDeviceModel, key: &str) -> Option<Dpi> {
+        if let Some(dpi) = props.get(key) {
+            if let Ok(dpi) = dpi.parse::<u16>() {
+                return Some(Dpi::from(dpi));
+            } else {
+                self.warn(format!(
+                    "Invalid DPI {} for {}.{} ignored",
+                    dpi,
+                    model.get_name(),
+                    key
+                ));
+            }
+        }
+
+        None
+    }
+
+    fn parse_u8(&self, props: &Properties, model: &dyn GDeviceModel, key: &str) -> Option<u8> {
+        if let Some(number) = props.get(key) {
+            if let Ok(number) = number.parse::<u8>() {
+                return Some(number);
+            } else {
+                self.warn(format!(
+                    "Invalid number {} for {}.{} ignored",
+                    number,
+                    model.get_name(),
+                    key
+                ));
+            }
+        }
+
+        None
+    }
+
+    fn parse_gamma(&self, props: &Properties, model: &dyn GDeviceModel) -> Option<f32> {
+        if let Some(gamma) = props.get("gamma") {
+            match gamma.parse::<f32>() {
+                Ok(gamma) if gamma.is_finite() && gamma > 0.0 => return Some(gamma),
+                _ => self.warn(format!(
+                    "Invalid gamma {} for {}.gamma ignored",
+                    gamma,
+                    model.get_name()
+                )),
+            }
+        }
+
+        None
+    }
+
+    /// Parse factor for colors from 0 to 1
+    fn parse_intensity(&self, props: &Properties, model: &dyn GDeviceModel) -> Option<f32> {
+        if let Some(intensity) = props.get("intensity") {
+            match intensity.parse::<f32>() {
+                Ok(intensity) if (0.0..=1.0).contains(&intensity) => return Some(intensity),
+                _ => self.warn(format!(
+                    "Invalid intensity {} for {}.intensity ignored",
+                    intensity,
+                    model.get_name()
+                )),
+            }
+        }
+
+        None
+    }
+
     fn parse_bool(&self, props: &Properties, model: &dyn GDeviceModel, key: &str) -> Option<bool> {
         if let Some(boolean) = props.get(key) {
             if let Ok(boolean) = boolean.parse::<bool>() {
                 return Some(boolean);
             } else {
-                warn!(
-                    "Invalid speed {} for {}.{} ignored",
+                self.warn(format!(
+                    "Invalid boolean {} for {}.{} ignored",
                     boolean,
                     model.get_name(),
                     key
-                );
+                ));
             }
         }
 
         None
     }
 
-    pub fn save_command(&mut self, model: &dyn GDeviceModel, cmd: Command) {
-        let mut section = self.0.with_section(Some(model.get_name()));
+    pub fn save_command(&mut self, model: &dyn GDeviceModel, serial: &str, cmd: Command) {
+        let section_name = self.section_name_for(model, serial);
+        let replaces_lighting = cmd.is_lighting()
+            && !matches!(
+                cmd,
+                Command::ColorSector(_, Some(_)) | Command::ColorSectorRgbw(_, Some(_))
+            );
+        if replaces_lighting {
+            // Saved colors are already the ones shown. Colors of a single sector keep the
+            // intensity, which still applies to the other sectors.
+            self.ini
+                .delete_from(Some(section_name.clone()), "intensity");
+        }
+        let mut section = self.ini.with_section(Some(section_name));
 
         match cmd {
             Command::ColorSector(color, Some(sector)) => {
@@ -184,12 +812,42 @@ impl Config {
                     .set("type", "static")
                     .set(format!("color-{sector}"), color.to_hex());
             }
+            Command::ColorSectors(colors) => {
+                let mut setter = section.set("type", "static");
+                for (i, color) in colors.iter().enumerate() {
+                    setter = setter.set(format!("color-{i}"), color.to_hex());
+                }
+            }
+            Command::Gradient(start, end) => {
+                let mut setter = section.set("type", "static");
+                for (i, color) in start.gradient(&end, model.get_sectors()).iter().enumerate() {
+                    setter = setter.set(format!("color-{i}"), color.to_hex());
+                }
+            }
+            Command::TripleColor(left, middle, right) => {
+                section
+                    .set("type", "triple")
+                    .set("color-0", left.to_hex())
+                    .set("color-1", middle.to_hex())
+                    .set("color-2", right.to_hex());
+            }
             Command::ColorSector(color, None) => {
                 let mut setter = section.set("type", "static-all");
                 for i in 0..model.get_sectors() {
                     setter = setter.set(format!("color-{i}"), color.to_hex());
                 }
             }
+            Command::ColorSectorRgbw(color, Some(sector)) => {
+                section
+                    .set("type", "static")
+                    .set(format!("color-{sector}"), color.to_hex());
+            }
+            Command::ColorSectorRgbw(color, None) => {
+                let mut setter = section.set("type", "static-all");
+                for i in 0..model.get_sectors() {
+                    setter = setter.set(format!("color-{i}"), color.to_hex());
+                }
+            }
             Command::Breathe(color, speed, brightness) => {
                 let section = section.set("type", "breathe").set("color", color.to_hex());
                 let section = Self::set_speed(section, speed);
@@ -201,34 +859,64 @@ impl Config {
                 Self::set_brightness(section, brightness);
             }
             Command::Wave(direction, speed, brightness) => {
-                let section = section.set("type", "wave").set(
-                    "direction",
-                    match direction {
-                        Direction::LeftToRight => "left-to-right",
-                        Direction::RightToLeft => "right-to-left",
-                        Direction::CenterToEdge => "center-to-edge",
-                        Direction::EdgeToCenter => "edge-to-center",
-                    },
-                );
+                let section = section
+                    .set("type", "wave")
+                    .set("direction", direction.to_string());
                 let section = Self::set_speed(section, speed);
                 Self::set_brightness(section, brightness);
             }
-            Command::StartEffect(state) => {
+            Command::Reactive(color, speed) => {
                 section
-                    .set("type", "startEffect")
-                    .set("state", if state { "true" } else { "false" });
+                    .set("type", "reactive")
+                    .set("color", color.to_hex())
+                    .set("speed", speed.0.to_string());
+            }
+            Command::Off => {
+                section.set("type", "off");
+            }
+            Command::StartEffect(state) => {
+                // Keep saved effect, which is replayed on power-up
+                section.set("start_effect", if state { "true" } else { "false" });
             }
             Command::Blend(speed, brightness) => {
                 let section = section.set("type", "blend");
                 let section = Self::set_speed(section, speed);
                 Self::set_brightness(section, brightness);
             }
+            Command::SetBrightness(brightness) => {
+                // Keep saved effect and change only its brightness
+                Self::set_brightness(&mut section, Some(brightness));
+            }
             Command::Dpi(dpi) => {
-                section.set("type", "dpi").set("dpi", dpi.0.to_string());
+                // DPI is independent of lighting, so keep type of saved effect
+                section.set("dpi", dpi.0.to_string());
             }
+            Command::SelectOnboardProfile(slot) => {
+                // Onboard profile is independent of lighting, so keep type of saved effect
+                section.set("onboard-profile", slot.to_string());
+            }
+            // DPI stages are stored by device firmware, raw reports and test patterns are
+            // never saved
+            Command::DpiStages(_)
+            | Command::DpiStage(_)
+            | Command::SetFeatureReport(_, _)
+            | Command::TestPattern => return,
         }
-        self.0.write_to_file(CONFIG_PATH).unwrap_or_else(|err| {
-            error!("Failed to write config file {}: {:?}", CONFIG_PATH, err);
+        self.save();
+    }
+
+    /// Write config to file at `path`
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        self.ini.write_to_file(path)
+    }
+
+    fn save(&self) {
+        self.save_to(&self.path).unwrap_or_else(|err| {
+            error!(
+                "Failed to write config file {}: {:?}",
+                self.path.display(),
+                err
+            );
         });
     }
 
@@ -254,3 +942,344 @@ impl Config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DeviceType;
+
+    struct TestModel;
+
+    impl GDeviceModel for TestModel {
+        fn get_sectors(&self) -> u8 {
+            3
+        }
+
+        fn get_default_color(&self) -> RgbColor {
+            RgbColor(0xff, 0xff, 0xff)
+        }
+
+        fn get_name(&self) -> &'static str {
+            "Test"
+        }
+
+        fn get_type(&self) -> DeviceType {
+            DeviceType::Keyboard
+        }
+
+        fn usb_product_id(&self) -> u16 {
+            0
+        }
+    }
+
+    fn config(ini: &str) -> Config {
+        Config::from_str(ini)
+    }
+
+    fn test_models() -> Vec<GDeviceModelRef> {
+        vec![std::sync::Arc::new(TestModel)]
+    }
+
+    #[test]
+    fn serial_section_overlapping_model_section_is_reported() {
+        let config = config("[Test]\ntype=static-all\ncolor=ff0000\n[Test@1]\ntype=off\n");
+        assert_eq!(
+            config.check(&test_models()),
+            vec!["Config section [Test@1] overrides [Test]".to_string()]
+        );
+    }
+
+    #[test]
+    fn serial_section_overlapping_profile_section_is_reported() {
+        let config = config("[gdevd]\nprofile=night\n[Test:night]\ntype=off\n[Test@1]\ntype=off\n");
+        assert_eq!(
+            config.check(&test_models()),
+            vec!["Config section [Test@1] overrides [Test:night]".to_string()]
+        );
+    }
+
+    #[test]
+    fn serial_section_overlapping_all_sections_is_reported_once() {
+        let config = config(
+            "[gdevd]\nprofile=night\n[Test]\ntype=off\n[Test:night]\ntype=off\n\
+             [Test@1]\ntype=off\n",
+        );
+        assert_eq!(
+            config.check(&test_models()),
+            vec!["Config section [Test@1] overrides [Test:night], [Test]".to_string()]
+        );
+        config.commands_for(&TestModel, "1");
+        assert_eq!(config.warnings.borrow().len(), 1);
+    }
+
+    #[test]
+    fn profile_section_overlapping_model_section_is_no_warning() {
+        let config = config("[gdevd]\nprofile=night\n[Test]\ntype=off\n[Test:night]\ntype=off\n");
+        assert!(config.check(&test_models()).is_empty());
+    }
+
+    #[test]
+    fn inactive_profile_section_does_not_overlap() {
+        let config = config("[Test:night]\ntype=off\n[Test@1]\ntype=off\n");
+        assert!(config.check(&test_models()).is_empty());
+    }
+
+    #[test]
+    fn lock_of_serial_section_applies_to_that_device_only() {
+        let config = config("[Test]\ntype=off\n[Test@1]\ntype=off\nlock=true\n");
+        assert!(config.is_locked(&TestModel, "1"));
+        assert!(!config.is_locked(&TestModel, "2"));
+    }
+
+    /// Save commands for device and read config of device back
+    fn round_trip(cmds: Vec<Command>) -> Vec<Command> {
+        let mut config = config("");
+        for cmd in cmds {
+            config.save_command(&TestModel, "1", cmd);
+        }
+        config.commands_for(&TestModel, "1")
+    }
+
+    #[test]
+    fn breathe_round_trip() {
+        let cmd = Command::Breathe(
+            RgbColor(0x10, 0x20, 0x30),
+            Some(Speed(2000)),
+            Some(Brightness(50)),
+        );
+        assert_eq!(round_trip(vec![cmd.clone()]), vec![cmd]);
+    }
+
+    #[test]
+    fn every_command_round_trips() {
+        let red = RgbColor(0xff, 0x00, 0x00);
+        let green = RgbColor(0x00, 0xff, 0x00);
+        let blue = RgbColor(0x00, 0x00, 0xff);
+        let white = TestModel.get_default_color();
+        let speed = Some(Speed(2000));
+        let brightness = Some(Brightness(50));
+        let sectors = |colors: [&RgbColor; 3]| -> Vec<Command> {
+            colors
+                .iter()
+                .enumerate()
+                .map(|(i, color)| Command::ColorSector((*color).clone(), Some(i as u8)))
+                .collect()
+        };
+        let same = |cmd: Command| (vec![cmd.clone()], vec![cmd]);
+        let cases: Vec<(Vec<Command>, Vec<Command>)> = vec![
+            same(Command::ColorSector(red.clone(), None)),
+            (
+                vec![Command::ColorSector(green.clone(), Some(1))],
+                sectors([&white, &green, &white]),
+            ),
+            same(Command::ColorSectorRgbw(RgbwColor(1, 2, 3, 4), None)),
+            (
+                vec![Command::ColorSectorRgbw(RgbwColor(1, 2, 3, 4), Some(2))],
+                vec![
+                    Command::ColorSector(white.clone(), Some(0)),
+                    Command::ColorSector(white.clone(), Some(1)),
+                    Command::ColorSectorRgbw(RgbwColor(1, 2, 3, 4), Some(2)),
+                ],
+            ),
+            (
+                vec![Command::ColorSectors(vec![
+                    red.clone(),
+                    green.clone(),
+                    blue.clone(),
+                ])],
+                sectors([&red, &green, &blue]),
+            ),
+            same(Command::TripleColor(
+                red.clone(),
+                green.clone(),
+                blue.clone(),
+            )),
+            (
+                vec![Command::Gradient(red.clone(), blue.clone())],
+                sectors([&red, &RgbColor(0x80, 0x00, 0x80), &blue]),
+            ),
+            same(Command::Breathe(red.clone(), speed, brightness)),
+            same(Command::Cycle(speed, brightness)),
+            same(Command::Cycle(None, None)),
+            same(Command::Wave(Direction::EdgeToCenter, speed, brightness)),
+            same(Command::Blend(speed, brightness)),
+            same(Command::Reactive(red.clone(), Speed(2000))),
+            (
+                vec![
+                    Command::Cycle(speed, None),
+                    Command::SetBrightness(Brightness(20)),
+                ],
+                vec![Command::Cycle(speed, Some(Brightness(20)))],
+            ),
+            same(Command::Off),
+            (
+                vec![Command::Off, Command::StartEffect(false)],
+                vec![Command::Off, Command::StartEffect(false)],
+            ),
+            (
+                vec![Command::Off, Command::Dpi(Dpi(800))],
+                vec![Command::Off, Command::Dpi(Dpi(800))],
+            ),
+            same(Command::SelectOnboardProfile(2)),
+            (vec![Command::TestPattern], vec![]),
+            (vec![Command::DpiStages(vec![Dpi(400)])], vec![]),
+            (vec![Command::DpiStage(1)], vec![]),
+            (vec![Command::SetFeatureReport(0x0c, vec![0x3a])], vec![]),
+        ];
+
+        for (saved, expected) in cases {
+            assert_eq!(round_trip(saved.clone()), expected, "saved {:?}", saved);
+        }
+    }
+
+    #[test]
+    fn onboard_profile_keeps_effect() {
+        let effect = Command::Cycle(Some(Speed(2000)), None);
+        assert_eq!(
+            round_trip(vec![effect.clone(), Command::SelectOnboardProfile(2)]),
+            vec![effect, Command::SelectOnboardProfile(2)]
+        );
+    }
+
+    #[test]
+    fn onboard_profile_of_older_versions_is_read() {
+        let config = config("[Test]\ntype=onboardProfile\nprofile=2\n");
+        assert_eq!(
+            config.commands_for(&TestModel, "1"),
+            vec![Command::SelectOnboardProfile(2)]
+        );
+    }
+
+    #[test]
+    fn breathe_of_older_versions_is_read() {
+        let config = config("[Test]\ntype=breath\ncolor=102030\n");
+        assert_eq!(
+            config.commands_for(&TestModel, "1"),
+            vec![Command::Breathe(RgbColor(0x10, 0x20, 0x30), None, None)]
+        );
+    }
+
+    #[test]
+    fn active_profile_overrides_model_section() {
+        let config = config(
+            "[gdevd]\nprofile=night\n[Test]\ntype=static-all\ncolor=ff0000\n\
+             [Test:night]\ntype=static-all\ncolor=0000ff\n",
+        );
+        assert_eq!(
+            config.commands_for(&TestModel, "1"),
+            vec![Command::ColorSector(RgbColor(0x00, 0x00, 0xff), None)]
+        );
+    }
+
+    #[test]
+    fn serial_section_overrides_active_profile() {
+        let config = config(
+            "[gdevd]\nprofile=night\n[Test:night]\ntype=static-all\ncolor=0000ff\n\
+             [Test@1]\ntype=static-all\ncolor=00ff00\n",
+        );
+        assert_eq!(
+            config.commands_for(&TestModel, "1"),
+            vec![Command::ColorSector(RgbColor(0x00, 0xff, 0x00), None)]
+        );
+        assert_eq!(
+            config.commands_for(&TestModel, "2"),
+            vec![Command::ColorSector(RgbColor(0x00, 0x00, 0xff), None)]
+        );
+    }
+
+    #[test]
+    fn type_is_inferred_from_sector_colors() {
+        let config = config("[Test]\ncolor-0=ff0000\ncolor-1=00ff00\ncolor-2=0000ff\n");
+        assert_eq!(
+            config.commands_for(&TestModel, "1"),
+            vec![
+                Command::ColorSector(RgbColor(0xff, 0x00, 0x00), Some(0)),
+                Command::ColorSector(RgbColor(0x00, 0xff, 0x00), Some(1)),
+                Command::ColorSector(RgbColor(0x00, 0x00, 0xff), Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_is_inferred_from_color() {
+        let config = config("[Test]\ncolor=ff0000\n");
+        assert_eq!(
+            config.commands_for(&TestModel, "1"),
+            vec![Command::ColorSector(RgbColor(0xff, 0x00, 0x00), None)]
+        );
+    }
+
+    #[test]
+    fn explicit_type_is_not_inferred() {
+        let config = config("[Test]\ntype=off\ncolor=ff0000\n");
+        assert_eq!(config.commands_for(&TestModel, "1"), vec![Command::Off]);
+    }
+
+    #[test]
+    fn section_without_type_and_colors_is_empty() {
+        let config = config("[Test]\nbrightness=50\n");
+        assert!(config.commands_for(&TestModel, "1").is_empty());
+    }
+
+    #[test]
+    fn missing_sector_colors_are_ignored_with_warning() {
+        let config = config("[Test]\ntype=static\ncolors=ff0000,00ff00,0000ff\ncolor-4=ffffff\n");
+        assert_eq!(
+            config.commands_for(&TestModel, "1"),
+            vec![
+                Command::ColorSector(RgbColor(0xff, 0x00, 0x00), Some(0)),
+                Command::ColorSector(RgbColor(0x00, 0xff, 0x00), Some(1)),
+                Command::ColorSector(RgbColor(0x00, 0x00, 0xff), Some(2)),
+            ]
+        );
+        assert_eq!(
+            config.warnings.borrow().clone(),
+            vec!["Test.color-4 ignored because Test has only 3 sectors".to_string()]
+        );
+    }
+
+    #[test]
+    fn intensity_scales_sector_colors() {
+        let config = config(
+            "[Test]\ntype=static\ncolor-0=ff0000\ncolors=00ff00,0000ff,ffffff\nintensity=0.5\n",
+        );
+        assert_eq!(
+            config.commands_for(&TestModel, "1"),
+            vec![
+                Command::ColorSector(RgbColor(0x80, 0x00, 0x00), Some(0)),
+                Command::ColorSector(RgbColor(0x00, 0x00, 0x80), Some(1)),
+                Command::ColorSector(RgbColor(0x80, 0x80, 0x80), Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn intensity_scales_white_channel() {
+        let config = config("[Test]\ntype=static-all\ncolor=ff000064\nintensity=0.5\n");
+        assert_eq!(
+            config.commands_for(&TestModel, "1"),
+            vec![Command::ColorSectorRgbw(
+                RgbwColor(0x80, 0x00, 0x00, 0x32),
+                None
+            )]
+        );
+    }
+
+    #[test]
+    fn saving_sector_color_keeps_intensity() {
+        let mut config = config("[Test]\ntype=static-all\ncolor=ff0000\nintensity=0.5\n");
+        config.save_command(
+            &TestModel,
+            "1",
+            Command::ColorSector(RgbColor(0x00, 0xff, 0x00), Some(1)),
+        );
+        assert_eq!(config.ini.get_from(Some("Test"), "intensity"), Some("0.5"));
+
+        config.save_command(
+            &TestModel,
+            "1",
+            Command::ColorSector(RgbColor(0, 0, 0), None),
+        );
+        assert_eq!(config.ini.get_from(Some("Test"), "intensity"), None);
+    }
+}
@@ -3,21 +3,39 @@ use std::sync::Arc;
 
 use rusb::{Context, Device};
 
-use crate::drivers::{DeviceDescription, GUsbDriver};
+use crate::drivers::{
+    dry_run_effect, CommandSink, DeviceDescription, GUsbDriver, LogitechReport,
+    DEFAULT_ENDPOINT_ADDRESS, DEFAULT_INTERFACE, DEFAULT_USB_TIMEOUT,
+};
 use crate::{
     Brightness, Command, CommandError, CommandResult, DeviceType, Direction, Dpi, GDevice,
-    GDeviceDriver, GDeviceModel, GDeviceModelRef, RgbColor, Speed, UsbDevice,
+    GDeviceDriver, GDeviceModel, GDeviceModelRef, RgbColor, Speed, Stability, UsbDevice,
 };
 
 #[allow(unused)]
 const DEFAULT_DIRECTION: Direction = Direction::RightToLeft;
 
+// The firmware has up to 5 DPI stages, but their reports are not implemented yet, so
+// `num_dpi_stages` keeps reporting none.
+
+// Periods are milliseconds, big endian for breathe and cycle, little endian for wave and blend
 const DEVICE: DeviceDescription = DeviceDescription {
+    name: "G203 LIGHTSYNC",
     product_id: 0xc092,
     min_speed: Speed(1000),
     default_speed: Speed(10000), // 11000 ???
     max_speed: Speed(20000),     // ???
-    min_dpi: Dpi(50),
+    min_dpi: Dpi(200),
+    max_dpi: Dpi(8000),
+    default_brightness: Brightness(100),
+    meaningful_ack: true,
+    via_receiver: false,
+    usb_timeout: DEFAULT_USB_TIMEOUT,
+    expects_ack: true,
+    // Range is narrower than speeds accepted by gdevctl and DBus
+    clamp_speed: true,
+    interface: DEFAULT_INTERFACE,
+    endpoint_address: DEFAULT_ENDPOINT_ADDRESS,
 };
 
 pub struct G203LightsyncDriver {
@@ -45,6 +63,14 @@ impl GDeviceDriver for G203LightsyncDriver {
             }) as Box<dyn GDevice>
         })
     }
+
+    fn stability(&self) -> Stability {
+        Stability::Stable
+    }
+
+    fn dry_run(&self, sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+        dry_run_effect(sink, &DeviceCommand::for_reset().bytes, cmd, send_effect)
+    }
 }
 
 pub struct G203LightsyncModel;
@@ -71,7 +97,7 @@ impl GDeviceModel for G203LightsyncModel {
     }
 
     fn get_name(&self) -> &'static str {
-        "G203 LIGHTSYNC"
+        DEVICE.name
     }
 
     fn get_type(&self) -> DeviceType {
@@ -81,6 +107,22 @@ impl GDeviceModel for G203LightsyncModel {
     fn usb_product_id(&self) -> u16 {
         DEVICE.product_id
     }
+
+    fn default_brightness(&self) -> Brightness {
+        DEVICE.default_brightness
+    }
+
+    fn supports_dpi(&self) -> bool {
+        true
+    }
+
+    fn supports_triple_color(&self) -> bool {
+        true
+    }
+
+    fn supports_sector_colors(&self) -> bool {
+        false
+    }
 }
 
 pub struct G203LightsyncDevice {
@@ -88,167 +130,159 @@ pub struct G203LightsyncDevice {
     model: GDeviceModelRef,
 }
 
-struct DeviceCommand {
-    bytes: [u8; 20],
-}
+/// Feature index of lighting effects
+pub(super) const FEATURE: u8 = 0x0e;
+
+pub(super) struct DeviceCommand;
 //00 00 00 00 00 00 00 01 00 00 00
 impl DeviceCommand {
-    pub fn for_color(color: RgbColor) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0e,
+    pub fn for_color(color: RgbColor) -> LogitechReport {
+        LogitechReport::long(
+            FEATURE,
             0x1b,
-            0,
-            0x01,
-            color.red(),
-            color.green(),
-            color.blue(),
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            1,
-        ])
-    }
-
-    pub fn for_reset() -> Self {
-        Self::new(&[0x10, 0xff, 0x0e, 0x5b, 0x01, 0x03, 0x05])
-    }
-
-    pub fn for_breathe(color: RgbColor, speed: Speed, brightness: Brightness) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0e,
+            &[
+                0,
+                0x01,
+                color.red(),
+                color.green(),
+                color.blue(),
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                1,
+            ],
+        )
+    }
+
+    pub fn for_reset() -> LogitechReport {
+        LogitechReport::short(FEATURE, 0x5b, &[0x01, 0x03, 0x05])
+    }
+
+    pub fn for_breathe(color: RgbColor, speed: Speed, brightness: Brightness) -> LogitechReport {
+        LogitechReport::long(
+            FEATURE,
             0x1b,
-            0,
-            0x04,
-            color.red(),
-            color.green(),
-            color.blue(),
-            (speed.0 >> 8) as u8,
-            speed.0 as u8,
-            0,
-            brightness.0,
-            0,
-            0,
-            0,
-            1,
-        ])
-    }
-
-    pub fn for_cycle(speed: Speed, brightness: Brightness) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0e,
+            &[
+                0,
+                0x04,
+                color.red(),
+                color.green(),
+                color.blue(),
+                (speed.0 >> 8) as u8,
+                speed.0 as u8,
+                0,
+                brightness.0,
+                0,
+                0,
+                0,
+                1,
+            ],
+        )
+    }
+
+    pub fn for_cycle(speed: Speed, brightness: Brightness) -> LogitechReport {
+        LogitechReport::long(
+            FEATURE,
             0x1b,
-            0,
-            0x02,
-            0,
-            0,
-            0,
-            0,
-            0,
-            (speed.0 >> 8) as u8,
-            speed.0 as u8,
-            brightness.0,
-            0,
-            0,
-            1,
-        ])
-    }
-
-    pub fn for_wave(direction: Direction, speed: Speed, brightness: Brightness) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0e,
+            &[
+                0,
+                0x02,
+                0,
+                0,
+                0,
+                0,
+                0,
+                (speed.0 >> 8) as u8,
+                speed.0 as u8,
+                brightness.0,
+                0,
+                0,
+                1,
+            ],
+        )
+    }
+
+    pub fn for_wave(direction: Direction, speed: Speed, brightness: Brightness) -> LogitechReport {
+        LogitechReport::long(
+            FEATURE,
             0x1b,
-            0,
-            0x03,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            speed.0 as u8,
-            direction as u8,
-            brightness.0,
-            (speed.0 >> 8) as u8,
-            1,
-        ])
+            &[
+                0,
+                0x03,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                speed.0 as u8,
+                direction as u8,
+                brightness.0,
+                (speed.0 >> 8) as u8,
+                1,
+            ],
+        )
     }
 
     #[allow(unused)]
-    pub fn for_blend(speed: Speed, brightness: Brightness) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0e,
+    pub fn for_blend(speed: Speed, brightness: Brightness) -> LogitechReport {
+        LogitechReport::long(
+            FEATURE,
             0x1b,
-            0,
-            0x06,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            speed.0 as u8,
-            (speed.0 >> 8) as u8,
-            brightness.0,
-            0,
-            1,
-        ])
+            &[
+                0,
+                0x06,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                speed.0 as u8,
+                (speed.0 >> 8) as u8,
+                brightness.0,
+                0,
+                1,
+            ],
+        )
     }
 
-    #[allow(unused)]
-    pub fn for_triple(left: RgbColor, middle: RgbColor, right: RgbColor) -> Self {
-        // TODO: Add command
-        // After that call: VALUE=0x211 11ff127b00000000000000000000000000000000
-        Self::new(&[
-            0x11,
-            0xff,
+    pub fn for_triple(left: RgbColor, middle: RgbColor, right: RgbColor) -> LogitechReport {
+        LogitechReport::long(
             0x12,
             0x1b,
-            0x01,
-            left.red(),
-            left.green(),
-            left.blue(),
-            0x02,
-            middle.red(),
-            middle.green(),
-            middle.blue(),
-            0x03,
-            right.red(),
-            right.green(),
-            right.blue(),
-        ])
-    }
-
-    pub fn for_start_effect(state: bool) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0e,
-            0x3b,
-            0x01,
-            0x00,
-            0x01,
-            if state { 1 } else { 2 },
-        ])
+            &[
+                0x01,
+                left.red(),
+                left.green(),
+                left.blue(),
+                0x02,
+                middle.red(),
+                middle.green(),
+                middle.blue(),
+                0x03,
+                right.red(),
+                right.green(),
+                right.blue(),
+            ],
+        )
+    }
+
+    /// Apply zone colors sent by `for_triple`
+    pub fn for_triple_commit() -> LogitechReport {
+        LogitechReport::long(0x12, 0x7b, &[])
     }
 
-    pub fn new(b: &[u8]) -> Self {
-        let mut bytes = [0; 20];
-        bytes[0..b.len()].copy_from_slice(b);
-        Self { bytes }
+    pub fn for_start_effect(state: bool) -> LogitechReport {
+        LogitechReport::long(
+            FEATURE,
+            0x3b,
+            &[0x01, 0x00, 0x01, if state { 1 } else { 2 }],
+        )
     }
 }
 
@@ -289,41 +323,206 @@ impl GDevice for G203LightsyncDevice {
         self.model.clone()
     }
 
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        self.driver.probe_features()
+    }
+
     fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
-        use Command::*;
-
-        let interface = self.driver.open_interface()?;
-        interface.send_data(&DeviceCommand::for_reset().bytes)?;
-
-        match cmd {
-            Breathe(rgb, speed, brightness) => interface.send_data(
-                &DeviceCommand::for_breathe(
-                    rgb,
-                    DEVICE.get_speed(speed)?,
-                    brightness.unwrap_or_default(),
-                )
-                .bytes,
-            ),
-            Cycle(speed, brightness) => interface.send_data(
-                &DeviceCommand::for_cycle(DEVICE.get_speed(speed)?, brightness.unwrap_or_default())
-                    .bytes,
-            ),
-            Wave(direction, speed, brightness) => interface.send_data(
-                &DeviceCommand::for_wave(
-                    direction,
-                    DEVICE.get_speed(speed)?,
-                    brightness.unwrap_or_default(),
-                )
-                .bytes,
-            ),
-            StartEffect(state) => {
-                interface.send_data(&DeviceCommand::for_start_effect(state).bytes)
-            }
-            ColorSector(color, sector) => {
-                sector_unsupported(sector)?;
-                interface.send_data(&DeviceCommand::for_color(color).bytes)
-            }
-            _ => Err(CommandError::InvalidCommand),
+        // DPI is independent of lighting and must not reset it
+        if let Command::Dpi(dpi) = cmd {
+            return self.driver.send_dpi(dpi);
         }
+        self.driver.send_with_recovery(
+            &DeviceCommand::for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_frame(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_frame(
+            &DeviceCommand::for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_command_batch(&mut self, cmds: &[Command]) -> CommandResult<()> {
+        let (dpis, cmds): (Vec<Command>, Vec<Command>) = cmds
+            .iter()
+            .cloned()
+            .partition(|cmd| matches!(cmd, Command::Dpi(_)));
+        if !cmds.is_empty() {
+            self.driver.send_batch_with_recovery(
+                &DeviceCommand::for_reset().bytes,
+                &cmds,
+                self.model.get_default_color(),
+                send_effect,
+            )?;
+        }
+        for cmd in dpis {
+            self.send_command(cmd)?;
+        }
+        Ok(())
+    }
+}
+
+fn send_effect(sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+    use Command::*;
+
+    match cmd {
+        Breathe(rgb, speed, brightness) => sink.send(
+            &DeviceCommand::for_breathe(
+                rgb,
+                DEVICE.get_speed(speed)?,
+                brightness.unwrap_or(DEVICE.default_brightness),
+            )
+            .bytes,
+        ),
+        Cycle(speed, brightness) => sink.send(
+            &DeviceCommand::for_cycle(
+                DEVICE.get_speed(speed)?,
+                brightness.unwrap_or(DEVICE.default_brightness),
+            )
+            .bytes,
+        ),
+        Wave(direction, speed, brightness) => sink.send(
+            &DeviceCommand::for_wave(
+                direction,
+                DEVICE.get_speed(speed)?,
+                brightness.unwrap_or(DEVICE.default_brightness),
+            )
+            .bytes,
+        ),
+        StartEffect(state) => sink.send(&DeviceCommand::for_start_effect(state).bytes),
+        ColorSector(color, sector) => {
+            sector_unsupported(sector)?;
+            sink.send(&DeviceCommand::for_color(color).bytes)
+        }
+        TripleColor(left, middle, right) => {
+            sink.send(&DeviceCommand::for_triple(left, middle, right).bytes)?;
+            sink.send(&DeviceCommand::for_triple_commit().bytes)
+        }
+        SetFeatureReport(feature, params) => sink.send_feature_report(feature, &params),
+        cmd => Err(DEVICE.unsupported(&cmd)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::{padded, RecordingSink};
+
+    fn sent(cmd: Command) -> Vec<Vec<u8>> {
+        let sink = RecordingSink::default();
+        send_effect(&sink, cmd).unwrap();
+        sink.reports()
+    }
+
+    #[test]
+    fn color() {
+        assert_eq!(
+            sent(Command::ColorSector(RgbColor(0xff, 0x80, 0x00), None)),
+            vec![padded(&[
+                0x11, 0xff, 0x0e, 0x1b, 0x00, 0x01, 0xff, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x01
+            ])]
+        );
+    }
+
+    #[test]
+    fn color_of_sector_is_rejected() {
+        let sink = RecordingSink::default();
+        let result = send_effect(&sink, Command::ColorSector(RgbColor(0, 0, 0), Some(0)));
+        assert!(matches!(
+            result,
+            Err(CommandError::InvalidArgument("sector", _))
+        ));
+        assert!(sink.reports().is_empty());
+    }
+
+    #[test]
+    fn breathe() {
+        assert_eq!(
+            sent(Command::Breathe(
+                RgbColor(0x10, 0x20, 0x30),
+                Some(Speed(0x1388)),
+                Some(Brightness(50))
+            )),
+            vec![padded(&[
+                0x11, 0xff, 0x0e, 0x1b, 0x00, 0x04, 0x10, 0x20, 0x30, 0x13, 0x88, 0x00, 0x32, 0x00,
+                0x00, 0x00, 0x01
+            ])]
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        assert_eq!(
+            sent(Command::Cycle(Some(Speed(0x2710)), Some(Brightness(100)))),
+            vec![padded(&[
+                0x11, 0xff, 0x0e, 0x1b, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x27, 0x10, 0x64,
+                0x00, 0x00, 0x01
+            ])]
+        );
+    }
+
+    #[test]
+    fn triple_color_is_committed() {
+        assert_eq!(
+            sent(Command::TripleColor(
+                RgbColor(0x01, 0x02, 0x03),
+                RgbColor(0x04, 0x05, 0x06),
+                RgbColor(0x07, 0x08, 0x09)
+            )),
+            vec![
+                padded(&[
+                    0x11, 0xff, 0x12, 0x1b, 0x01, 0x01, 0x02, 0x03, 0x02, 0x04, 0x05, 0x06, 0x03,
+                    0x07, 0x08, 0x09
+                ]),
+                padded(&[0x11, 0xff, 0x12, 0x7b]),
+            ]
+        );
+    }
+
+    #[test]
+    fn reset() {
+        assert_eq!(
+            DeviceCommand::for_reset().bytes.to_vec(),
+            padded(&[0x10, 0xff, 0x0e, 0x5b, 0x01, 0x03, 0x05])
+        );
+    }
+
+    #[test]
+    fn wave() {
+        assert_eq!(
+            sent(Command::Wave(
+                Direction::RightToLeft,
+                Some(Speed(0x1388)),
+                Some(Brightness(100))
+            )),
+            vec![padded(&[
+                0x11, 0xff, 0x0e, 0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x88, 0x06,
+                0x64, 0x13, 0x01
+            ])]
+        );
+    }
+
+    #[test]
+    fn start_effect() {
+        assert_eq!(
+            sent(Command::StartEffect(true)),
+            vec![padded(&[0x11, 0xff, 0x0e, 0x3b, 0x01, 0x00, 0x01, 0x01])]
+        );
+    }
+
+    #[test]
+    fn speed_is_clamped() {
+        assert_eq!(
+            sent(Command::Cycle(Some(Speed(10)), Some(Brightness(100))))[0][11..13],
+            [0x03, 0xe8]
+        );
     }
 }
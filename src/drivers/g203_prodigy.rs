@@ -0,0 +1,338 @@
+use std::fmt;
+use std::sync::Arc;
+
+use rusb::{Context, Device};
+
+use crate::drivers::g203_lightsync::{self, FEATURE};
+use crate::drivers::{
+    dry_run_effect, CommandSink, DeviceDescription, GUsbDriver, LogitechReport,
+    DEFAULT_ENDPOINT_ADDRESS, DEFAULT_INTERFACE, DEFAULT_USB_TIMEOUT,
+};
+use crate::{
+    Brightness, Command, CommandResult, DeviceType, Dpi, EffectKind, GDevice, GDeviceDriver,
+    GDeviceModel, GDeviceModelRef, RgbColor, Speed, UsbDevice,
+};
+
+// Effect periods are milliseconds as in the G203 LIGHTSYNC reports it is derived from
+const DEVICE: DeviceDescription = DeviceDescription {
+    name: "G203 Prodigy",
+    product_id: 0xc084,
+    min_speed: Speed(1000),
+    default_speed: Speed(10000),
+    max_speed: Speed(20000), // ???
+    min_dpi: Dpi(200),
+    max_dpi: Dpi(8000),
+    default_brightness: Brightness(100),
+    meaningful_ack: true,
+    via_receiver: false,
+    usb_timeout: DEFAULT_USB_TIMEOUT,
+    expects_ack: true,
+    // Range is narrower than speeds accepted by gdevctl and DBus
+    clamp_speed: true,
+    interface: DEFAULT_INTERFACE,
+    endpoint_address: DEFAULT_ENDPOINT_ADDRESS,
+};
+
+pub struct G203ProdigyDriver {
+    model: GDeviceModelRef,
+}
+
+impl Default for G203ProdigyDriver {
+    fn default() -> Self {
+        Self {
+            model: Arc::new(G203ProdigyModel),
+        }
+    }
+}
+
+impl GDeviceDriver for G203ProdigyDriver {
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn open_device(&self, device: &Device<Context>) -> Option<Box<dyn GDevice>> {
+        GUsbDriver::open_device(&DEVICE, device).map(|driver| {
+            Box::new(G203ProdigyDevice {
+                driver,
+                model: self.model.clone(),
+            }) as Box<dyn GDevice>
+        })
+    }
+
+    fn dry_run(&self, sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+        dry_run_effect(
+            sink,
+            &g203_lightsync::DeviceCommand::for_reset().bytes,
+            cmd,
+            send_effect,
+        )
+    }
+}
+
+pub struct G203ProdigyModel;
+
+impl G203ProdigyModel {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for G203ProdigyModel {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl GDeviceModel for G203ProdigyModel {
+    /// Logo
+    fn get_sectors(&self) -> u8 {
+        1
+    }
+
+    fn get_default_color(&self) -> RgbColor {
+        RgbColor(0x00, 0xA9, 0xE0)
+    }
+
+    fn get_name(&self) -> &'static str {
+        DEVICE.name
+    }
+
+    fn get_type(&self) -> DeviceType {
+        DeviceType::Mouse
+    }
+
+    fn usb_product_id(&self) -> u16 {
+        DEVICE.product_id
+    }
+
+    fn default_brightness(&self) -> Brightness {
+        DEVICE.default_brightness
+    }
+
+    fn supported_effects(&self) -> &'static [EffectKind] {
+        &[EffectKind::Static, EffectKind::Breathe, EffectKind::Cycle]
+    }
+
+    fn supports_dpi(&self) -> bool {
+        true
+    }
+}
+
+pub struct G203ProdigyDevice {
+    driver: GUsbDriver,
+    model: GDeviceModelRef,
+}
+
+/// Lighting reports of the Prodigy
+///
+/// Compared to the LIGHTSYNC the effect function is 0x3c instead of 0x1b and the effect
+/// indices differ: breathe is 0x03 instead of 0x04. Static colors end with 0x02 instead of
+/// the trailing 0x01 of the LIGHTSYNC. Breathe and cycle parameters use the LIGHTSYNC
+/// layout without its trailing byte. There is no wave effect and only a single zone.
+/// Reset and start effect reports are shared with the LIGHTSYNC.
+struct DeviceCommand;
+
+impl DeviceCommand {
+    fn effect(params: &[u8]) -> LogitechReport {
+        LogitechReport::long(FEATURE, 0x3c, params)
+    }
+
+    pub fn for_color(color: RgbColor) -> LogitechReport {
+        Self::effect(&[0, 0x01, color.red(), color.green(), color.blue(), 0x02])
+    }
+
+    pub fn for_breathe(color: RgbColor, speed: Speed, brightness: Brightness) -> LogitechReport {
+        Self::effect(&[
+            0,
+            0x03,
+            color.red(),
+            color.green(),
+            color.blue(),
+            (speed.0 >> 8) as u8,
+            speed.0 as u8,
+            0,
+            brightness.0,
+        ])
+    }
+
+    pub fn for_cycle(speed: Speed, brightness: Brightness) -> LogitechReport {
+        Self::effect(&[
+            0,
+            0x02,
+            0,
+            0,
+            0,
+            0,
+            0,
+            (speed.0 >> 8) as u8,
+            speed.0 as u8,
+            brightness.0,
+        ])
+    }
+}
+
+impl fmt::Display for G203ProdigyDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{} [{}]",
+            self.get_model().get_name(),
+            self.serial_number()
+        ))
+    }
+}
+
+impl GDevice for G203ProdigyDevice {
+    fn dev(&self) -> &UsbDevice {
+        self.driver.dev()
+    }
+
+    fn serial_number(&self) -> &str {
+        self.driver.serial_number()
+    }
+
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        self.driver.probe_features()
+    }
+
+    fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
+        // DPI is independent of lighting and must not reset it
+        if let Command::Dpi(dpi) = cmd {
+            return self.driver.send_dpi(dpi);
+        }
+        self.driver.send_with_recovery(
+            &g203_lightsync::DeviceCommand::for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_frame(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_frame(
+            &g203_lightsync::DeviceCommand::for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_command_batch(&mut self, cmds: &[Command]) -> CommandResult<()> {
+        let (dpis, cmds): (Vec<Command>, Vec<Command>) = cmds
+            .iter()
+            .cloned()
+            .partition(|cmd| matches!(cmd, Command::Dpi(_)));
+        if !cmds.is_empty() {
+            self.driver.send_batch_with_recovery(
+                &g203_lightsync::DeviceCommand::for_reset().bytes,
+                &cmds,
+                self.model.get_default_color(),
+                send_effect,
+            )?;
+        }
+        for cmd in dpis {
+            self.send_command(cmd)?;
+        }
+        Ok(())
+    }
+}
+
+fn send_effect(sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+    use Command::*;
+
+    match cmd {
+        ColorSector(color, sector) => {
+            DEVICE.check_single_zone(sector)?;
+            sink.send(&DeviceCommand::for_color(color).bytes)
+        }
+        Breathe(rgb, speed, brightness) => sink.send(
+            &DeviceCommand::for_breathe(
+                rgb,
+                DEVICE.get_speed(speed)?,
+                brightness.unwrap_or(DEVICE.default_brightness),
+            )
+            .bytes,
+        ),
+        Cycle(speed, brightness) => sink.send(
+            &DeviceCommand::for_cycle(
+                DEVICE.get_speed(speed)?,
+                brightness.unwrap_or(DEVICE.default_brightness),
+            )
+            .bytes,
+        ),
+        StartEffect(state) => {
+            sink.send(&g203_lightsync::DeviceCommand::for_start_effect(state).bytes)
+        }
+        SetFeatureReport(feature, params) => sink.send_feature_report(feature, &params),
+        cmd => Err(DEVICE.unsupported(&cmd)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::{padded, RecordingSink};
+    use crate::CommandError;
+
+    fn sent(cmd: Command) -> Vec<Vec<u8>> {
+        let sink = RecordingSink::default();
+        send_effect(&sink, cmd).unwrap();
+        sink.reports()
+    }
+
+    #[test]
+    fn color_ends_with_0x02() {
+        assert_eq!(
+            sent(Command::ColorSector(RgbColor(0xff, 0x80, 0x00), None)),
+            vec![padded(&[
+                0x11, 0xff, 0x0e, 0x3c, 0x00, 0x01, 0xff, 0x80, 0x00, 0x02
+            ])]
+        );
+    }
+
+    #[test]
+    fn breathe() {
+        assert_eq!(
+            sent(Command::Breathe(
+                RgbColor(0x10, 0x20, 0x30),
+                Some(Speed(0x1388)),
+                Some(Brightness(50))
+            )),
+            vec![padded(&[
+                0x11, 0xff, 0x0e, 0x3c, 0x00, 0x03, 0x10, 0x20, 0x30, 0x13, 0x88, 0x00, 0x32
+            ])]
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        assert_eq!(
+            sent(Command::Cycle(Some(Speed(0x2710)), Some(Brightness(100)))),
+            vec![padded(&[
+                0x11, 0xff, 0x0e, 0x3c, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x27, 0x10, 0x64
+            ])]
+        );
+    }
+
+    #[test]
+    fn sector_0_is_the_single_zone() {
+        assert_eq!(
+            sent(Command::ColorSector(RgbColor(0xff, 0x80, 0x00), Some(0))),
+            sent(Command::ColorSector(RgbColor(0xff, 0x80, 0x00), None))
+        );
+    }
+
+    #[test]
+    fn other_sectors_are_rejected() {
+        let sink = RecordingSink::default();
+        let result = send_effect(&sink, Command::ColorSector(RgbColor(0, 0, 0), Some(1)));
+        assert!(matches!(
+            result,
+            Err(CommandError::InvalidArgument("sector", _))
+        ));
+        assert!(sink.reports().is_empty());
+    }
+}
@@ -3,20 +3,36 @@ use std::sync::Arc;
 
 use rusb::{Context, Device};
 
-use crate::drivers::{DeviceDescription, GUsbDriver};
+use crate::drivers::{
+    dry_run_effect, send_zone_effect, CommandSink, DeviceDescription, GUsbDriver, ZoneEffects,
+    DEFAULT_ENDPOINT_ADDRESS, DEFAULT_INTERFACE, DEFAULT_USB_TIMEOUT,
+};
 use crate::{
-    Brightness, Command, CommandError, CommandResult, DeviceType, Direction, Dpi, GDevice,
-    GDeviceDriver, GDeviceModel, GDeviceModelRef, RgbColor, Speed, UsbDevice,
+    Brightness, Command, CommandResult, DeviceType, Dpi, EffectKind, GDevice, GDeviceDriver,
+    GDeviceModel, GDeviceModelRef, RgbColor, Speed, Stability, UsbDevice,
 };
 
 const DEFAULT_RGB: RgbColor = RgbColor(0x00, 0xA9, 0xE0);
 
+const EFFECTS: ZoneEffects = ZoneEffects::new(0x0c);
+
+// Effect periods are big endian milliseconds like in g810-led, so speed units are 1 ms
 const DEVICE: DeviceDescription = DeviceDescription {
+    name: "G213",
     product_id: 0xc336,
     min_speed: Speed(32), // ???
     default_speed: Speed(1000),
     max_speed: Speed(u16::MAX), // ???
     min_dpi: Dpi(u16::MAX),
+    max_dpi: Dpi(0),
+    default_brightness: Brightness(100),
+    meaningful_ack: true,
+    via_receiver: false,
+    usb_timeout: DEFAULT_USB_TIMEOUT,
+    expects_ack: true,
+    clamp_speed: false,
+    interface: DEFAULT_INTERFACE,
+    endpoint_address: DEFAULT_ENDPOINT_ADDRESS,
 };
 
 pub struct G213Driver {
@@ -44,6 +60,14 @@ impl GDeviceDriver for G213Driver {
             }) as Box<dyn GDevice>
         })
     }
+
+    fn stability(&self) -> Stability {
+        Stability::Stable
+    }
+
+    fn dry_run(&self, sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+        dry_run_effect(sink, &EFFECTS.for_reset().bytes, cmd, send_effect)
+    }
 }
 
 pub struct G213Model;
@@ -70,7 +94,7 @@ impl GDeviceModel for G213Model {
     }
 
     fn get_name(&self) -> &'static str {
-        "G213"
+        DEVICE.name
     }
 
     fn get_type(&self) -> DeviceType {
@@ -80,6 +104,20 @@ impl GDeviceModel for G213Model {
     fn usb_product_id(&self) -> u16 {
         DEVICE.product_id
     }
+
+    fn default_brightness(&self) -> Brightness {
+        DEVICE.default_brightness
+    }
+
+    fn supported_effects(&self) -> &'static [EffectKind] {
+        &[
+            EffectKind::Static,
+            EffectKind::Breathe,
+            EffectKind::Cycle,
+            EffectKind::Wave,
+            EffectKind::Reactive,
+        ]
+    }
 }
 
 pub struct G213Device {
@@ -87,122 +125,6 @@ pub struct G213Device {
     model: GDeviceModelRef,
 }
 
-struct DeviceCommand {
-    bytes: [u8; 20],
-}
-
-impl DeviceCommand {
-    pub fn for_color(color: RgbColor) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0c,
-            0x3a,
-            0,
-            0x01,
-            color.red(),
-            color.green(),
-            color.blue(),
-            0x02,
-        ])
-    }
-
-    pub fn for_region_color(region: u8, color: RgbColor) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0c,
-            0x3a,
-            region + 1,
-            0x01,
-            color.red(),
-            color.green(),
-            color.blue(),
-            0x02,
-        ])
-    }
-
-    pub fn for_reset() -> Self {
-        Self::new(&[0x11, 0xff, 0x0c, 0x0d])
-    }
-
-    pub fn for_breathe(color: RgbColor, speed: Speed, brightness: Brightness) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0c,
-            0x3a,
-            0,
-            0x02,
-            color.red(),
-            color.green(),
-            color.blue(),
-            (speed.0 >> 8) as u8,
-            speed.0 as u8,
-            0,
-            brightness.0,
-        ])
-    }
-
-    pub fn for_cycle(speed: Speed, brightness: Brightness) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0c,
-            0x3a,
-            0,
-            0x03,
-            0xff,
-            0xff,
-            0xff,
-            0,
-            0,
-            (speed.0 >> 8) as u8,
-            speed.0 as u8,
-            brightness.0,
-        ])
-    }
-
-    pub fn for_wave(direction: Direction, speed: Speed, brightness: Brightness) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0c,
-            0x3a,
-            0,
-            0x04,
-            0x00,
-            0x00,
-            0x00,
-            0,
-            0,
-            0,
-            speed.0 as u8,
-            direction as u8,
-            brightness.0,
-            (speed.0 >> 8) as u8,
-        ])
-    }
-
-    pub fn for_start_effect(state: bool) -> Self {
-        Self::new(&[
-            0x11,
-            0xff,
-            0x0c,
-            0x5d,
-            0x00,
-            0x01,
-            if state { 1 } else { 2 },
-        ])
-    }
-
-    pub fn new(b: &[u8]) -> Self {
-        let mut bytes = [0; 20];
-        bytes[0..b.len()].copy_from_slice(b);
-        Self { bytes }
-    }
-}
-
 impl fmt::Display for G213Device {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!(
@@ -226,50 +148,186 @@ impl GDevice for G213Device {
         self.model.clone()
     }
 
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        self.driver.probe_features()
+    }
+
     fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
-        use Command::*;
-
-        let interface = self.driver.open_interface()?;
-        interface.send_data(&DeviceCommand::for_reset().bytes)?;
-
-        match cmd {
-            ColorSector(rgb, sector) => {
-                if let Some(sector) = sector {
-                    if sector > 4 {
-                        return Err(CommandError::InvalidArgument(
-                            "sector",
-                            format!("{sector} > 4"),
-                        ));
-                    }
-                    interface.send_data(&DeviceCommand::for_region_color(sector, rgb).bytes)
-                } else {
-                    interface.send_data(&DeviceCommand::for_color(rgb).bytes)
-                }
-            }
-            Breathe(rgb, speed, brightness) => interface.send_data(
-                &DeviceCommand::for_breathe(
-                    rgb,
-                    DEVICE.get_speed(speed)?,
-                    brightness.unwrap_or_default(),
-                )
-                .bytes,
-            ),
-            Cycle(speed, brightness) => interface.send_data(
-                &DeviceCommand::for_cycle(DEVICE.get_speed(speed)?, brightness.unwrap_or_default())
-                    .bytes,
-            ),
-            Wave(direction, speed, brightness) => interface.send_data(
-                &DeviceCommand::for_wave(
-                    direction,
-                    DEVICE.get_speed(speed)?,
-                    brightness.unwrap_or_default(),
-                )
-                .bytes,
-            ),
-            StartEffect(state) => {
-                interface.send_data(&DeviceCommand::for_start_effect(state).bytes)
-            }
-            _ => Err(CommandError::InvalidCommand),
-        }
+        self.driver.send_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_frame(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_frame(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_command_batch(&mut self, cmds: &[Command]) -> CommandResult<()> {
+        self.driver.send_batch_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmds,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+}
+
+fn send_effect(sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+    send_zone_effect(sink, &DEVICE, &EFFECTS, 5, cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::{padded, RecordingSink};
+    use crate::{CommandError, Direction};
+
+    fn sent(cmd: Command) -> Vec<Vec<u8>> {
+        let sink = RecordingSink::default();
+        send_effect(&sink, cmd).unwrap();
+        sink.reports()
+    }
+
+    #[test]
+    fn color_of_all_sectors() {
+        assert_eq!(
+            sent(Command::ColorSector(RgbColor(0xff, 0x80, 0x00), None)),
+            vec![padded(&[
+                0x11, 0xff, 0x0c, 0x3a, 0x00, 0x01, 0xff, 0x80, 0x00, 0x02
+            ])]
+        );
+    }
+
+    #[test]
+    fn color_of_sector_is_sent_to_region() {
+        assert_eq!(
+            sent(Command::ColorSector(RgbColor(0x00, 0x00, 0xff), Some(4))),
+            vec![padded(&[
+                0x11, 0xff, 0x0c, 0x3a, 0x05, 0x01, 0x00, 0x00, 0xff, 0x02
+            ])]
+        );
+    }
+
+    #[test]
+    fn sector_out_of_range() {
+        let sink = RecordingSink::default();
+        let result = send_effect(&sink, Command::ColorSector(RgbColor(0, 0, 0), Some(5)));
+        assert!(matches!(
+            result,
+            Err(CommandError::InvalidArgument("sector", _))
+        ));
+        assert!(sink.reports().is_empty());
+    }
+
+    #[test]
+    fn colors_of_sectors() {
+        assert_eq!(
+            sent(Command::ColorSectors(vec![
+                RgbColor(0x01, 0x02, 0x03),
+                RgbColor(0x04, 0x05, 0x06),
+            ])),
+            vec![
+                padded(&[0x11, 0xff, 0x0c, 0x3a, 0x01, 0x01, 0x01, 0x02, 0x03, 0x02]),
+                padded(&[0x11, 0xff, 0x0c, 0x3a, 0x02, 0x01, 0x04, 0x05, 0x06, 0x02]),
+            ]
+        );
+    }
+
+    #[test]
+    fn breathe() {
+        assert_eq!(
+            sent(Command::Breathe(
+                RgbColor(0x10, 0x20, 0x30),
+                Some(Speed(1000)),
+                Some(Brightness(50))
+            )),
+            vec![padded(&[
+                0x11, 0xff, 0x0c, 0x3a, 0x00, 0x02, 0x10, 0x20, 0x30, 0x03, 0xe8, 0x00, 0x32
+            ])]
+        );
+    }
+
+    #[test]
+    fn cycle_with_defaults() {
+        assert_eq!(
+            sent(Command::Cycle(None, None)),
+            vec![padded(&[
+                0x11, 0xff, 0x0c, 0x3a, 0x00, 0x03, 0xff, 0xff, 0xff, 0x00, 0x00, 0x03, 0xe8, 0x64
+            ])]
+        );
+    }
+
+    #[test]
+    fn wave() {
+        assert_eq!(
+            sent(Command::Wave(
+                Direction::LeftToRight,
+                Some(Speed(0x1234)),
+                Some(Brightness(100))
+            )),
+            vec![padded(&[
+                0x11,
+                0xff,
+                0x0c,
+                0x3a,
+                0x00,
+                0x04,
+                0x00,
+                0x00,
+                0x00,
+                0x00,
+                0x00,
+                0x00,
+                0x34,
+                Direction::LeftToRight as u8,
+                0x64,
+                0x12
+            ])]
+        );
+    }
+
+    #[test]
+    fn reset() {
+        assert_eq!(
+            EFFECTS.for_reset().bytes.to_vec(),
+            padded(&[0x11, 0xff, 0x0c, 0x0d])
+        );
+    }
+
+    #[test]
+    fn reactive() {
+        assert_eq!(
+            sent(Command::Reactive(RgbColor(0x10, 0x20, 0x30), Speed(0x1388))),
+            vec![padded(&[
+                0x11, 0xff, 0x0c, 0x3a, 0x00, 0x09, 0x10, 0x20, 0x30, 0x13, 0x88
+            ])]
+        );
+    }
+
+    #[test]
+    fn start_effect() {
+        assert_eq!(
+            sent(Command::StartEffect(false)),
+            vec![padded(&[0x11, 0xff, 0x0c, 0x5d, 0x00, 0x01, 0x02])]
+        );
+    }
+
+    #[test]
+    fn speed_out_of_range_is_rejected() {
+        let sink = RecordingSink::default();
+        let result = send_effect(&sink, Command::Cycle(Some(Speed(10)), None));
+        assert!(matches!(
+            result,
+            Err(CommandError::InvalidArgument("speed", _))
+        ));
+        assert!(sink.reports().is_empty());
     }
 }
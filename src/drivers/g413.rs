@@ -0,0 +1,267 @@
+use std::fmt;
+use std::sync::Arc;
+
+use rusb::{Context, Device};
+
+use crate::drivers::{
+    dry_run_effect, CommandSink, DeviceDescription, GUsbDriver, ZoneEffects,
+    DEFAULT_ENDPOINT_ADDRESS, DEFAULT_INTERFACE, DEFAULT_USB_TIMEOUT,
+};
+use crate::{
+    Brightness, Command, CommandResult, DeviceType, Dpi, EffectKind, GDevice, GDeviceDriver,
+    GDeviceModel, GDeviceModelRef, RgbColor, Speed, UsbDevice,
+};
+
+const DEFAULT_RGB: RgbColor = RgbColor(0x00, 0xA9, 0xE0);
+
+const EFFECTS: ZoneEffects = ZoneEffects::new(0x0c);
+
+const DEVICE: DeviceDescription = DeviceDescription {
+    name: "G413",
+    product_id: 0xc33a,
+    min_speed: Speed(32), // ???
+    default_speed: Speed(1000),
+    max_speed: Speed(u16::MAX), // ???
+    min_dpi: Dpi(u16::MAX),
+    max_dpi: Dpi(0),
+    default_brightness: Brightness(100),
+    meaningful_ack: true,
+    via_receiver: false,
+    usb_timeout: DEFAULT_USB_TIMEOUT,
+    expects_ack: true,
+    clamp_speed: false,
+    interface: DEFAULT_INTERFACE,
+    endpoint_address: DEFAULT_ENDPOINT_ADDRESS,
+};
+
+pub struct G413Driver {
+    model: GDeviceModelRef,
+}
+
+impl Default for G413Driver {
+    fn default() -> Self {
+        Self {
+            model: Arc::new(G413Model),
+        }
+    }
+}
+
+impl GDeviceDriver for G413Driver {
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn open_device(&self, device: &Device<Context>) -> Option<Box<dyn GDevice>> {
+        GUsbDriver::open_device(&DEVICE, device).map(|driver| {
+            Box::new(G413Device {
+                driver,
+                model: self.model.clone(),
+            }) as Box<dyn GDevice>
+        })
+    }
+
+    fn dry_run(&self, sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+        dry_run_effect(sink, &EFFECTS.for_reset().bytes, cmd, send_effect)
+    }
+}
+
+pub struct G413Model;
+
+impl G413Model {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for G413Model {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl GDeviceModel for G413Model {
+    fn get_sectors(&self) -> u8 {
+        1
+    }
+
+    fn get_default_color(&self) -> RgbColor {
+        DEFAULT_RGB
+    }
+
+    fn get_name(&self) -> &'static str {
+        DEVICE.name
+    }
+
+    fn get_type(&self) -> DeviceType {
+        DeviceType::Keyboard
+    }
+
+    fn usb_product_id(&self) -> u16 {
+        DEVICE.product_id
+    }
+
+    fn default_brightness(&self) -> Brightness {
+        DEVICE.default_brightness
+    }
+
+    fn supported_effects(&self) -> &'static [EffectKind] {
+        &[EffectKind::Static, EffectKind::Breathe, EffectKind::Cycle]
+    }
+}
+
+pub struct G413Device {
+    driver: GUsbDriver,
+    model: GDeviceModelRef,
+}
+
+impl fmt::Display for G413Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{} [{}]",
+            self.get_model().get_name(),
+            self.serial_number()
+        ))
+    }
+}
+
+impl GDevice for G413Device {
+    fn dev(&self) -> &UsbDevice {
+        self.driver.dev()
+    }
+
+    fn serial_number(&self) -> &str {
+        self.driver.serial_number()
+    }
+
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        self.driver.probe_features()
+    }
+
+    fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_frame(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_frame(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_command_batch(&mut self, cmds: &[Command]) -> CommandResult<()> {
+        self.driver.send_batch_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmds,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+}
+
+fn send_effect(sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+    use Command::*;
+
+    match cmd {
+        ColorSector(rgb, sector) => {
+            DEVICE.check_single_zone(sector)?;
+            sink.send(&EFFECTS.for_color(rgb).bytes)
+        }
+        Breathe(rgb, speed, brightness) => sink.send(
+            &EFFECTS
+                .for_breathe(
+                    rgb,
+                    DEVICE.get_speed(speed)?,
+                    brightness.unwrap_or(DEVICE.default_brightness),
+                )
+                .bytes,
+        ),
+        Cycle(speed, brightness) => sink.send(
+            &EFFECTS
+                .for_cycle(
+                    DEVICE.get_speed(speed)?,
+                    brightness.unwrap_or(DEVICE.default_brightness),
+                )
+                .bytes,
+        ),
+        StartEffect(state) => sink.send(&EFFECTS.for_start_effect(state).bytes),
+        SetFeatureReport(feature, params) => sink.send_feature_report(feature, &params),
+        cmd => Err(DEVICE.unsupported(&cmd)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::{padded, RecordingSink};
+    use crate::CommandError;
+
+    fn sent(cmd: Command) -> Vec<Vec<u8>> {
+        let sink = RecordingSink::default();
+        send_effect(&sink, cmd).unwrap();
+        sink.reports()
+    }
+
+    #[test]
+    fn color() {
+        assert_eq!(
+            sent(Command::ColorSector(RgbColor(0xff, 0x80, 0x00), None)),
+            vec![padded(&[
+                0x11, 0xff, 0x0c, 0x3a, 0x00, 0x01, 0xff, 0x80, 0x00, 0x02
+            ])]
+        );
+    }
+
+    #[test]
+    fn breathe() {
+        assert_eq!(
+            sent(Command::Breathe(
+                RgbColor(0x10, 0x20, 0x30),
+                Some(Speed(1000)),
+                Some(Brightness(50))
+            )),
+            vec![padded(&[
+                0x11, 0xff, 0x0c, 0x3a, 0x00, 0x02, 0x10, 0x20, 0x30, 0x03, 0xe8, 0x00, 0x32
+            ])]
+        );
+    }
+
+    #[test]
+    fn cycle_with_defaults() {
+        assert_eq!(
+            sent(Command::Cycle(None, None)),
+            vec![padded(&[
+                0x11, 0xff, 0x0c, 0x3a, 0x00, 0x03, 0xff, 0xff, 0xff, 0x00, 0x00, 0x03, 0xe8, 0x64
+            ])]
+        );
+    }
+
+    #[test]
+    fn sector_0_is_the_single_zone() {
+        assert_eq!(
+            sent(Command::ColorSector(RgbColor(0xff, 0x80, 0x00), Some(0))),
+            sent(Command::ColorSector(RgbColor(0xff, 0x80, 0x00), None))
+        );
+    }
+
+    #[test]
+    fn other_sectors_are_rejected() {
+        let sink = RecordingSink::default();
+        let result = send_effect(&sink, Command::ColorSector(RgbColor(0, 0, 0), Some(1)));
+        assert!(matches!(
+            result,
+            Err(CommandError::InvalidArgument("sector", _))
+        ));
+        assert!(sink.reports().is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+use std::sync::Arc;
+
+use rusb::{Context, Device};
+
+use crate::drivers::{
+    CommandSink, DeviceDescription, GUsbDriver, DEFAULT_ENDPOINT_ADDRESS, DEFAULT_INTERFACE,
+    DEFAULT_USB_TIMEOUT,
+};
+use crate::{
+    Brightness, Command, CommandResult, DeviceType, Dpi, EffectKind, GDevice, GDeviceDriver,
+    GDeviceModel, GDeviceModelRef, RgbColor, Speed, UsbDevice,
+};
+
+const DEVICE: DeviceDescription = DeviceDescription {
+    name: "G502 HERO",
+    product_id: 0xc08b,
+    min_speed: Speed(u16::MAX),
+    default_speed: Speed(u16::MAX),
+    max_speed: Speed(u16::MAX),
+    min_dpi: Dpi(100),
+    max_dpi: Dpi(25600),
+    default_brightness: Brightness(100),
+    meaningful_ack: true,
+    via_receiver: false,
+    usb_timeout: DEFAULT_USB_TIMEOUT,
+    expects_ack: true,
+    clamp_speed: false,
+    interface: DEFAULT_INTERFACE,
+    endpoint_address: DEFAULT_ENDPOINT_ADDRESS,
+};
+
+pub struct G502HeroDriver {
+    model: GDeviceModelRef,
+}
+
+impl Default for G502HeroDriver {
+    fn default() -> Self {
+        Self {
+            model: Arc::new(G502HeroModel),
+        }
+    }
+}
+
+impl GDeviceDriver for G502HeroDriver {
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn open_device(&self, device: &Device<Context>) -> Option<Box<dyn GDevice>> {
+        GUsbDriver::open_device(&DEVICE, device).map(|driver| {
+            Box::new(G502HeroDevice {
+                driver,
+                model: self.model.clone(),
+            }) as Box<dyn GDevice>
+        })
+    }
+}
+
+pub struct G502HeroModel;
+
+impl G502HeroModel {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for G502HeroModel {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl GDeviceModel for G502HeroModel {
+    /// Lighting is not supported yet
+    fn get_sectors(&self) -> u8 {
+        0
+    }
+
+    fn get_default_color(&self) -> RgbColor {
+        RgbColor(0, 0, 0)
+    }
+
+    fn get_name(&self) -> &'static str {
+        DEVICE.name
+    }
+
+    fn get_type(&self) -> DeviceType {
+        DeviceType::Mouse
+    }
+
+    fn usb_product_id(&self) -> u16 {
+        DEVICE.product_id
+    }
+
+    fn supported_effects(&self) -> &'static [EffectKind] {
+        &[]
+    }
+
+    fn supports_dpi(&self) -> bool {
+        true
+    }
+}
+
+pub struct G502HeroDevice {
+    driver: GUsbDriver,
+    model: GDeviceModelRef,
+}
+
+impl fmt::Display for G502HeroDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{} [{}]",
+            self.get_model().get_name(),
+            self.serial_number()
+        ))
+    }
+}
+
+impl GDevice for G502HeroDevice {
+    fn dev(&self) -> &UsbDevice {
+        self.driver.dev()
+    }
+
+    fn serial_number(&self) -> &str {
+        self.driver.serial_number()
+    }
+
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        self.driver.probe_features()
+    }
+
+    fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
+        match cmd {
+            Command::Dpi(dpi) => self.driver.send_dpi(dpi),
+            Command::SetFeatureReport(feature, params) => self
+                .driver
+                .open_interface()?
+                .send_feature_report(feature, &params),
+            cmd => Err(DEVICE.unsupported(&cmd)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::{padded, LogitechReport};
+    use crate::CommandError;
+
+    #[test]
+    fn dpi_feature_request() {
+        assert_eq!(
+            LogitechReport::for_dpi_feature().bytes.to_vec(),
+            padded(&[0x11, 0xff, 0x00, 0x0a, 0x22, 0x01])
+        );
+    }
+
+    #[test]
+    fn max_dpi_report() {
+        assert_eq!(
+            LogitechReport::for_dpi(0x07, DEVICE.max_dpi).bytes.to_vec(),
+            padded(&[0x11, 0xff, 0x07, 0x3a, 0x00, 0x64, 0x00])
+        );
+    }
+
+    #[test]
+    fn dpi_range() {
+        assert!(DEVICE.check_dpi(Dpi(100)).is_ok());
+        assert!(DEVICE.check_dpi(Dpi(25600)).is_ok());
+        assert!(matches!(
+            DEVICE.check_dpi(Dpi(50)),
+            Err(CommandError::InvalidArgument("dpi", _))
+        ));
+        assert!(matches!(
+            DEVICE.check_dpi(Dpi(25601)),
+            Err(CommandError::InvalidArgument("dpi", _))
+        ));
+    }
+}
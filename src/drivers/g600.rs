@@ -0,0 +1,247 @@
+use std::fmt;
+use std::sync::Arc;
+
+use rusb::{Context, Device};
+
+use crate::drivers::{
+    dry_run_effect, send_zone_effect, CommandSink, DeviceDescription, GUsbDriver, ZoneEffects,
+    DEFAULT_ENDPOINT_ADDRESS, DEFAULT_INTERFACE, DEFAULT_USB_TIMEOUT,
+};
+use crate::{
+    Brightness, Command, CommandResult, DeviceType, Dpi, EffectKind, GDevice, GDeviceDriver,
+    GDeviceModel, GDeviceModelRef, RgbColor, Speed, UsbDevice,
+};
+
+const DEFAULT_RGB: RgbColor = RgbColor(0x00, 0xA9, 0xE0);
+
+// Encoding of the G213 zone effects at feature index 0x0e, not verified on a G600 ???
+const EFFECTS: ZoneEffects = ZoneEffects::new(0x0e);
+
+// Effect periods are assumed to be milliseconds like the other zone effects ???
+const DEVICE: DeviceDescription = DeviceDescription {
+    name: "G600",
+    product_id: 0xc24a,
+    min_speed: Speed(32), // ???
+    default_speed: Speed(1000),
+    max_speed: Speed(u16::MAX), // ???
+    min_dpi: Dpi(u16::MAX),
+    max_dpi: Dpi(0),
+    default_brightness: Brightness(100),
+    meaningful_ack: false,
+    via_receiver: false,
+    usb_timeout: DEFAULT_USB_TIMEOUT,
+    // Lighting reports are not answered on the interrupt endpoint
+    expects_ack: false,
+    clamp_speed: false,
+    interface: DEFAULT_INTERFACE,
+    endpoint_address: DEFAULT_ENDPOINT_ADDRESS,
+};
+
+pub struct G600Driver {
+    model: GDeviceModelRef,
+}
+
+impl Default for G600Driver {
+    fn default() -> Self {
+        Self {
+            model: Arc::new(G600Model),
+        }
+    }
+}
+
+impl GDeviceDriver for G600Driver {
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn open_device(&self, device: &Device<Context>) -> Option<Box<dyn GDevice>> {
+        GUsbDriver::open_device(&DEVICE, device).map(|driver| {
+            Box::new(G600Device {
+                driver,
+                model: self.model.clone(),
+            }) as Box<dyn GDevice>
+        })
+    }
+
+    fn dry_run(&self, sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+        dry_run_effect(sink, &EFFECTS.for_reset().bytes, cmd, send_effect)
+    }
+}
+
+pub struct G600Model;
+
+impl G600Model {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for G600Model {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl GDeviceModel for G600Model {
+    /// Side panels and scroll wheel
+    fn get_sectors(&self) -> u8 {
+        3
+    }
+
+    fn get_default_color(&self) -> RgbColor {
+        DEFAULT_RGB
+    }
+
+    fn get_name(&self) -> &'static str {
+        DEVICE.name
+    }
+
+    fn get_type(&self) -> DeviceType {
+        DeviceType::Mouse
+    }
+
+    fn usb_product_id(&self) -> u16 {
+        DEVICE.product_id
+    }
+
+    fn default_brightness(&self) -> Brightness {
+        DEVICE.default_brightness
+    }
+
+    fn supported_effects(&self) -> &'static [EffectKind] {
+        &[EffectKind::Static, EffectKind::Breathe, EffectKind::Cycle]
+    }
+}
+
+pub struct G600Device {
+    driver: GUsbDriver,
+    model: GDeviceModelRef,
+}
+
+impl fmt::Display for G600Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{} [{}]",
+            self.get_model().get_name(),
+            self.serial_number()
+        ))
+    }
+}
+
+impl GDevice for G600Device {
+    fn dev(&self) -> &UsbDevice {
+        self.driver.dev()
+    }
+
+    fn serial_number(&self) -> &str {
+        self.driver.serial_number()
+    }
+
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        self.driver.probe_features()
+    }
+
+    fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_frame(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_frame(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_command_batch(&mut self, cmds: &[Command]) -> CommandResult<()> {
+        self.driver.send_batch_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmds,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+}
+
+fn send_effect(sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+    send_zone_effect(sink, &DEVICE, &EFFECTS, 3, cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::{padded, RecordingSink};
+    use crate::CommandError;
+
+    fn sent(cmd: Command) -> Vec<Vec<u8>> {
+        let sink = RecordingSink::default();
+        send_effect(&sink, cmd).unwrap();
+        sink.reports()
+    }
+
+    #[test]
+    fn zone_colors() {
+        for zone in 0..3 {
+            assert_eq!(
+                sent(Command::ColorSector(RgbColor(0xff, 0x80, 0x00), Some(zone))),
+                vec![padded(&[
+                    0x11,
+                    0xff,
+                    0x0e,
+                    0x3a,
+                    zone + 1,
+                    0x01,
+                    0xff,
+                    0x80,
+                    0x00,
+                    0x02
+                ])]
+            );
+        }
+    }
+
+    #[test]
+    fn breathe() {
+        assert_eq!(
+            sent(Command::Breathe(
+                RgbColor(0x10, 0x20, 0x30),
+                Some(Speed(1000)),
+                Some(Brightness(50))
+            )),
+            vec![padded(&[
+                0x11, 0xff, 0x0e, 0x3a, 0x00, 0x02, 0x10, 0x20, 0x30, 0x03, 0xe8, 0x00, 0x32
+            ])]
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        assert_eq!(
+            sent(Command::Cycle(Some(Speed(0x2710)), Some(Brightness(100)))),
+            vec![padded(&[
+                0x11, 0xff, 0x0e, 0x3a, 0x00, 0x03, 0xff, 0xff, 0xff, 0x00, 0x00, 0x27, 0x10, 0x64
+            ])]
+        );
+    }
+
+    #[test]
+    fn sector_3_is_rejected() {
+        let sink = RecordingSink::default();
+        let result = send_effect(&sink, Command::ColorSector(RgbColor(0, 0, 0), Some(3)));
+        assert!(matches!(
+            result,
+            Err(CommandError::InvalidArgument("sector", _))
+        ));
+        assert!(sink.reports().is_empty());
+    }
+}
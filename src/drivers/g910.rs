@@ -0,0 +1,296 @@
+use std::fmt;
+use std::sync::Arc;
+
+use rusb::{Context, Device};
+
+use crate::drivers::{
+    dry_run_effect, send_keyboard_effect, CommandSink, DeviceDescription, GUsbDriver,
+    KeyboardEffects, LogitechReport, DEFAULT_ENDPOINT_ADDRESS, DEFAULT_INTERFACE,
+    DEFAULT_USB_TIMEOUT,
+};
+use crate::{
+    Brightness, Command, CommandResult, DeviceType, Dpi, GDevice, GDeviceDriver, GDeviceModel,
+    GDeviceModelRef, RgbColor, Speed, UsbDevice,
+};
+
+const DEFAULT_RGB: RgbColor = RgbColor(0x00, 0xA9, 0xE0);
+
+const DEVICE: DeviceDescription = DeviceDescription {
+    name: "G910",
+    product_id: 0xc32b,
+    min_speed: Speed(32), // ???
+    default_speed: Speed(1000),
+    max_speed: Speed(u16::MAX), // ???
+    min_dpi: Dpi(u16::MAX),
+    max_dpi: Dpi(0),
+    default_brightness: Brightness(100),
+    meaningful_ack: true,
+    via_receiver: false,
+    usb_timeout: DEFAULT_USB_TIMEOUT,
+    expects_ack: true,
+    clamp_speed: false,
+    interface: DEFAULT_INTERFACE,
+    endpoint_address: DEFAULT_ENDPOINT_ADDRESS,
+};
+
+pub struct G910Driver {
+    model: GDeviceModelRef,
+}
+
+impl Default for G910Driver {
+    fn default() -> Self {
+        Self {
+            model: Arc::new(G910Model),
+        }
+    }
+}
+
+impl GDeviceDriver for G910Driver {
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn open_device(&self, device: &Device<Context>) -> Option<Box<dyn GDevice>> {
+        GUsbDriver::open_device(&DEVICE, device).map(|driver| {
+            Box::new(G910Device {
+                driver,
+                model: self.model.clone(),
+            }) as Box<dyn GDevice>
+        })
+    }
+
+    fn dry_run(&self, sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+        dry_run_effect(sink, &EFFECTS.for_reset().bytes, cmd, send_effect)
+    }
+}
+
+pub struct G910Model;
+
+impl G910Model {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for G910Model {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl GDeviceModel for G910Model {
+    /// Keys and logo
+    fn get_sectors(&self) -> u8 {
+        2
+    }
+
+    fn get_default_color(&self) -> RgbColor {
+        DEFAULT_RGB
+    }
+
+    fn get_name(&self) -> &'static str {
+        DEVICE.name
+    }
+
+    fn get_type(&self) -> DeviceType {
+        DeviceType::Keyboard
+    }
+
+    fn usb_product_id(&self) -> u16 {
+        DEVICE.product_id
+    }
+
+    fn default_brightness(&self) -> Brightness {
+        DEVICE.default_brightness
+    }
+}
+
+pub struct G910Device {
+    driver: GUsbDriver,
+    model: GDeviceModelRef,
+}
+
+const EFFECTS: KeyboardEffects = KeyboardEffects::new(0x10, 0xff);
+
+/// Per-key reports
+struct DeviceCommand;
+
+impl DeviceCommand {
+    /// Color single key, needs `for_commit` afterwards
+    #[allow(unused)]
+    pub fn for_key_color(key_id: u8, color: RgbColor) -> LogitechReport {
+        LogitechReport::long(
+            0x0f,
+            0x3d,
+            &[
+                0x00,
+                0x01,
+                0x00,
+                0x01,
+                key_id,
+                color.red(),
+                color.green(),
+                color.blue(),
+            ],
+        )
+    }
+
+    /// Apply key colors sent by `for_key_color`
+    #[allow(unused)]
+    pub fn for_commit() -> LogitechReport {
+        LogitechReport::long(0x0f, 0x5d, &[])
+    }
+}
+
+impl fmt::Display for G910Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{} [{}]",
+            self.get_model().get_name(),
+            self.serial_number()
+        ))
+    }
+}
+
+impl GDevice for G910Device {
+    fn dev(&self) -> &UsbDevice {
+        self.driver.dev()
+    }
+
+    fn serial_number(&self) -> &str {
+        self.driver.serial_number()
+    }
+
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        self.driver.probe_features()
+    }
+
+    fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_frame(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_frame(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_command_batch(&mut self, cmds: &[Command]) -> CommandResult<()> {
+        self.driver.send_batch_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmds,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+}
+
+fn send_effect(sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+    send_keyboard_effect(sink, &DEVICE, &EFFECTS, cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::{padded, RecordingSink};
+    use crate::Direction;
+
+    fn sent(cmd: Command) -> Vec<Vec<u8>> {
+        let sink = RecordingSink::default();
+        send_effect(&sink, cmd).unwrap();
+        sink.reports()
+    }
+
+    #[test]
+    fn key_color() {
+        assert_eq!(
+            DeviceCommand::for_key_color(0x2c, RgbColor(0x10, 0x20, 0x30))
+                .bytes
+                .to_vec(),
+            padded(&[0x11, 0xff, 0x0f, 0x3d, 0x00, 0x01, 0x00, 0x01, 0x2c, 0x10, 0x20, 0x30])
+        );
+        assert_eq!(
+            DeviceCommand::for_commit().bytes.to_vec(),
+            padded(&[0x11, 0xff, 0x0f, 0x5d])
+        );
+    }
+
+    #[test]
+    fn color() {
+        assert_eq!(
+            sent(Command::ColorSector(RgbColor(0xff, 0x80, 0x00), None)),
+            vec![padded(&[
+                0x11, 0xff, 0x10, 0x3c, 0xff, 0x01, 0xff, 0x80, 0x00, 0x02
+            ])]
+        );
+    }
+
+    #[test]
+    fn region_color() {
+        assert_eq!(
+            sent(Command::ColorSector(RgbColor(0x00, 0x00, 0xff), Some(1))),
+            vec![padded(&[
+                0x11, 0xff, 0x10, 0x3c, 0x01, 0x01, 0x00, 0x00, 0xff, 0x02
+            ])]
+        );
+    }
+
+    #[test]
+    fn breathe() {
+        assert_eq!(
+            sent(Command::Breathe(
+                RgbColor(0x10, 0x20, 0x30),
+                Some(Speed(0x1388)),
+                Some(Brightness(50))
+            )),
+            vec![padded(&[
+                0x11, 0xff, 0x10, 0x3c, 0xff, 0x02, 0x10, 0x20, 0x30, 0x13, 0x88, 0x00, 0x32
+            ])]
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        assert_eq!(
+            sent(Command::Cycle(Some(Speed(0x2710)), Some(Brightness(100)))),
+            vec![padded(&[
+                0x11, 0xff, 0x10, 0x3c, 0xff, 0x03, 0xff, 0xff, 0xff, 0x00, 0x00, 0x27, 0x10, 0x64
+            ])]
+        );
+    }
+
+    #[test]
+    fn wave_addresses_keys_only() {
+        assert_eq!(
+            sent(Command::Wave(
+                Direction::CenterToEdge,
+                Some(Speed(0x1388)),
+                Some(Brightness(100))
+            )),
+            vec![padded(&[
+                0x11, 0xff, 0x10, 0x3c, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x88, 0x03,
+                0x64, 0x13
+            ])]
+        );
+    }
+
+    #[test]
+    fn start_effect() {
+        assert_eq!(
+            sent(Command::StartEffect(false)),
+            vec![padded(&[0x11, 0xff, 0x10, 0x5e, 0x00, 0x01, 0x02])]
+        );
+    }
+}
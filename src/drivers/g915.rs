@@ -0,0 +1,216 @@
+use std::fmt;
+use std::sync::Arc;
+
+use rusb::{Context, Device};
+
+use crate::drivers::{
+    dry_run_effect, send_keyboard_effect, CommandSink, DeviceDescription, GUsbDriver,
+    KeyboardEffects, DEFAULT_ENDPOINT_ADDRESS, DEFAULT_INTERFACE, DEFAULT_USB_TIMEOUT,
+    RECEIVER_DEVICE_INDEX,
+};
+use crate::{
+    Brightness, Command, CommandResult, DeviceType, Dpi, EffectKind, GDevice, GDeviceDriver,
+    GDeviceModel, GDeviceModelRef, RgbColor, Speed, UsbDevice,
+};
+
+const DEFAULT_RGB: RgbColor = RgbColor(0x00, 0xA9, 0xE0);
+
+// Feature index and effect encoding assumed to match the G910 ???
+const DEVICE: DeviceDescription = DeviceDescription {
+    name: "G915",
+    product_id: 0xc541,   // LIGHTSPEED receiver
+    min_speed: Speed(32), // ???
+    default_speed: Speed(1000),
+    max_speed: Speed(u16::MAX), // ???
+    min_dpi: Dpi(u16::MAX),
+    max_dpi: Dpi(0),
+    default_brightness: Brightness(100),
+    meaningful_ack: true,
+    via_receiver: true,
+    usb_timeout: DEFAULT_USB_TIMEOUT,
+    expects_ack: true,
+    clamp_speed: false,
+    interface: DEFAULT_INTERFACE,
+    endpoint_address: DEFAULT_ENDPOINT_ADDRESS,
+};
+
+pub struct G915Driver {
+    model: GDeviceModelRef,
+}
+
+impl Default for G915Driver {
+    fn default() -> Self {
+        Self {
+            model: Arc::new(G915Model),
+        }
+    }
+}
+
+impl GDeviceDriver for G915Driver {
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn open_device(&self, device: &Device<Context>) -> Option<Box<dyn GDevice>> {
+        GUsbDriver::open_device(&DEVICE, device).map(|driver| {
+            Box::new(G915Device {
+                driver,
+                model: self.model.clone(),
+            }) as Box<dyn GDevice>
+        })
+    }
+
+    fn dry_run(&self, sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+        dry_run_effect(sink, &EFFECTS.for_reset().bytes, cmd, send_effect)
+    }
+}
+
+pub struct G915Model;
+
+impl G915Model {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for G915Model {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl GDeviceModel for G915Model {
+    /// Keys and logo
+    fn get_sectors(&self) -> u8 {
+        2
+    }
+
+    fn get_default_color(&self) -> RgbColor {
+        DEFAULT_RGB
+    }
+
+    fn get_name(&self) -> &'static str {
+        DEVICE.name
+    }
+
+    fn get_type(&self) -> DeviceType {
+        DeviceType::Keyboard
+    }
+
+    fn usb_product_id(&self) -> u16 {
+        DEVICE.product_id
+    }
+
+    fn default_brightness(&self) -> Brightness {
+        DEVICE.default_brightness
+    }
+
+    fn supported_effects(&self) -> &'static [EffectKind] {
+        &[EffectKind::Static, EffectKind::Breathe, EffectKind::Cycle]
+    }
+}
+
+pub struct G915Device {
+    driver: GUsbDriver,
+    model: GDeviceModelRef,
+}
+
+const EFFECTS: KeyboardEffects = KeyboardEffects::new(0x10, RECEIVER_DEVICE_INDEX);
+
+impl fmt::Display for G915Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{} [{}]",
+            self.get_model().get_name(),
+            self.serial_number()
+        ))
+    }
+}
+
+impl GDevice for G915Device {
+    fn dev(&self) -> &UsbDevice {
+        self.driver.dev()
+    }
+
+    fn serial_number(&self) -> &str {
+        self.driver.serial_number()
+    }
+
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        self.driver.probe_features()
+    }
+
+    fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_frame(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_frame(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_command_batch(&mut self, cmds: &[Command]) -> CommandResult<()> {
+        self.driver.send_batch_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmds,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+}
+
+fn send_effect(sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+    match cmd {
+        // Wave and start effect of the G910 are unknown for the G915
+        Command::Wave(_, _, _) | Command::StartEffect(_) => Err(DEVICE.unsupported(&cmd)),
+        cmd => send_keyboard_effect(sink, &DEVICE, &EFFECTS, cmd),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::{padded, RecordingSink};
+
+    #[test]
+    fn dry_run_routes_to_paired_device() {
+        let sink = RecordingSink::default();
+        G915Driver::default()
+            .dry_run(
+                &sink,
+                Command::ColorSector(RgbColor(0xff, 0x80, 0x00), None),
+            )
+            .unwrap();
+        assert_eq!(
+            sink.reports(),
+            vec![
+                padded(&[0x11, 0x01, 0x10, 0x0d]),
+                padded(&[0x11, 0x01, 0x10, 0x3c, 0xff, 0x01, 0xff, 0x80, 0x00, 0x02]),
+            ]
+        );
+    }
+
+    #[test]
+    fn wave_is_unsupported() {
+        let sink = RecordingSink::default();
+        assert!(send_effect(
+            &sink,
+            Command::Wave(crate::Direction::LeftToRight, None, None)
+        )
+        .is_err());
+        assert!(sink.reports().is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+use std::sync::Arc;
+
+use rusb::{Context, Device};
+
+use crate::drivers::{
+    dry_run_effect, send_zone_effect, CommandSink, DeviceDescription, GUsbDriver, ZoneEffects,
+    DEFAULT_ENDPOINT_ADDRESS, DEFAULT_INTERFACE, DEFAULT_USB_TIMEOUT,
+};
+use crate::{
+    Brightness, Command, CommandResult, DeviceType, Dpi, EffectKind, GDevice, GDeviceDriver,
+    GDeviceModel, GDeviceModelRef, RgbColor, Speed, UsbDevice,
+};
+
+const DEFAULT_RGB: RgbColor = RgbColor(0x00, 0xA9, 0xE0);
+
+const EFFECTS: ZoneEffects = ZoneEffects::new(0x0c);
+
+// Effect reports are the ones of the G213, so speed units are 1 ms as well
+const DEVICE: DeviceDescription = DeviceDescription {
+    name: "G PRO",
+    product_id: 0xc339,
+    min_speed: Speed(32), // ???
+    default_speed: Speed(1000),
+    max_speed: Speed(u16::MAX), // ???
+    min_dpi: Dpi(u16::MAX),
+    max_dpi: Dpi(0),
+    default_brightness: Brightness(100),
+    meaningful_ack: true,
+    via_receiver: false,
+    usb_timeout: DEFAULT_USB_TIMEOUT,
+    expects_ack: true,
+    clamp_speed: false,
+    interface: DEFAULT_INTERFACE,
+    endpoint_address: DEFAULT_ENDPOINT_ADDRESS,
+};
+
+pub struct GProDriver {
+    model: GDeviceModelRef,
+}
+
+impl Default for GProDriver {
+    fn default() -> Self {
+        Self {
+            model: Arc::new(GProModel),
+        }
+    }
+}
+
+impl GDeviceDriver for GProDriver {
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn open_device(&self, device: &Device<Context>) -> Option<Box<dyn GDevice>> {
+        GUsbDriver::open_device(&DEVICE, device).map(|driver| {
+            Box::new(GProDevice {
+                driver,
+                model: self.model.clone(),
+            }) as Box<dyn GDevice>
+        })
+    }
+
+    fn dry_run(&self, sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+        dry_run_effect(sink, &EFFECTS.for_reset().bytes, cmd, send_effect)
+    }
+}
+
+pub struct GProModel;
+
+impl GProModel {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for GProModel {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl GDeviceModel for GProModel {
+    /// Keys and logo
+    fn get_sectors(&self) -> u8 {
+        2
+    }
+
+    fn get_default_color(&self) -> RgbColor {
+        DEFAULT_RGB
+    }
+
+    fn get_name(&self) -> &'static str {
+        DEVICE.name
+    }
+
+    fn get_type(&self) -> DeviceType {
+        DeviceType::Keyboard
+    }
+
+    fn usb_product_id(&self) -> u16 {
+        DEVICE.product_id
+    }
+
+    fn default_brightness(&self) -> Brightness {
+        DEVICE.default_brightness
+    }
+
+    fn supported_effects(&self) -> &'static [EffectKind] {
+        &[
+            EffectKind::Static,
+            EffectKind::Breathe,
+            EffectKind::Cycle,
+            EffectKind::Wave,
+            EffectKind::Reactive,
+        ]
+    }
+}
+
+pub struct GProDevice {
+    driver: GUsbDriver,
+    model: GDeviceModelRef,
+}
+
+impl fmt::Display for GProDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{} [{}]",
+            self.get_model().get_name(),
+            self.serial_number()
+        ))
+    }
+}
+
+impl GDevice for GProDevice {
+    fn dev(&self) -> &UsbDevice {
+        self.driver.dev()
+    }
+
+    fn serial_number(&self) -> &str {
+        self.driver.serial_number()
+    }
+
+    fn get_model(&self) -> GDeviceModelRef {
+        self.model.clone()
+    }
+
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        self.driver.probe_features()
+    }
+
+    fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_frame(&mut self, cmd: Command) -> CommandResult<()> {
+        self.driver.send_frame(
+            &EFFECTS.for_reset().bytes,
+            cmd,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+
+    fn send_command_batch(&mut self, cmds: &[Command]) -> CommandResult<()> {
+        self.driver.send_batch_with_recovery(
+            &EFFECTS.for_reset().bytes,
+            cmds,
+            self.model.get_default_color(),
+            send_effect,
+        )
+    }
+}
+
+fn send_effect(sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+    send_zone_effect(sink, &DEVICE, &EFFECTS, 2, cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::{padded, RecordingSink};
+    use crate::CommandError;
+
+    fn sent(cmd: Command) -> Vec<Vec<u8>> {
+        let sink = RecordingSink::default();
+        send_effect(&sink, cmd).unwrap();
+        sink.reports()
+    }
+
+    #[test]
+    fn region_colors() {
+        assert_eq!(
+            sent(Command::ColorSectors(vec![
+                RgbColor(0xff, 0x00, 0x00),
+                RgbColor(0x00, 0x00, 0xff)
+            ])),
+            vec![
+                padded(&[0x11, 0xff, 0x0c, 0x3a, 0x01, 0x01, 0xff, 0x00, 0x00, 0x02]),
+                padded(&[0x11, 0xff, 0x0c, 0x3a, 0x02, 0x01, 0x00, 0x00, 0xff, 0x02]),
+            ]
+        );
+    }
+
+    #[test]
+    fn breathe() {
+        assert_eq!(
+            sent(Command::Breathe(
+                RgbColor(0x10, 0x20, 0x30),
+                Some(Speed(1000)),
+                Some(Brightness(50))
+            )),
+            vec![padded(&[
+                0x11, 0xff, 0x0c, 0x3a, 0x00, 0x02, 0x10, 0x20, 0x30, 0x03, 0xe8, 0x00, 0x32
+            ])]
+        );
+    }
+
+    #[test]
+    fn sector_2_is_rejected() {
+        let sink = RecordingSink::default();
+        let result = send_effect(&sink, Command::ColorSector(RgbColor(0, 0, 0), Some(2)));
+        assert!(matches!(
+            result,
+            Err(CommandError::InvalidArgument("sector", _))
+        ));
+        assert!(sink.reports().is_empty());
+    }
+}
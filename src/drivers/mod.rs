@@ -1,33 +1,89 @@
+use std::borrow::Cow;
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::Duration;
 
 use quick_error::ResultExt;
 use rusb::{Context, Device, DeviceHandle};
 
 use crate::usb_ext::DetachedHandle;
-use crate::{CommandError, CommandResult, Dpi, Speed, UsbDevice};
+use crate::{
+    Brightness, Command, CommandError, CommandResult, Direction, Dpi, RgbColor, Speed, UsbDevice,
+};
 
 pub mod g203_lightsync;
+pub mod g203_prodigy;
 pub mod g213;
+pub mod g413;
+pub mod g502_hero;
+pub mod g600;
+pub mod g910;
+pub mod g915;
+pub mod gpro;
 
 // USB interface constants
-const ENDPOINT_ADDRESS: u8 = 0x82;
+const DEFAULT_ENDPOINT_ADDRESS: u8 = 0x82;
 const REQUEST_TYPE: u8 = 0x21; // request_type(Direction::Out, RequestType::Class, Recipient::Interface);
 const REQUEST: u8 = 0x09; // HID_REQ_SET_REPORT
 const VALUE: i32 = 0x0211;
-const INTERFACE: u8 = 0x0001;
+// Interface of HID++ reports for devices without special needs
+const DEFAULT_INTERFACE: u8 = 0x0001;
+// HID++ device index of first device paired with a receiver
+pub(crate) const RECEIVER_DEVICE_INDEX: u8 = 0x01;
+
+/// HID++ 2.0 feature id of AdjustableDPI
+const ADJUSTABLE_DPI: u16 = 0x2201;
+const FEATURE_SET: u16 = 0x0001;
+
+// Retries of transfers failing with transient errors
+const MAX_RETRIES: u32 = 3;
+const RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Timeout of USB transfers for devices without special needs
+const DEFAULT_USB_TIMEOUT: Duration = Duration::from_secs(5);
 
 struct DeviceDescription {
+    /// Model name
+    name: &'static str,
     product_id: u16,
     min_speed: Speed,
     default_speed: Speed,
     max_speed: Speed,
-    #[allow(unused)]
     min_dpi: Dpi,
+    max_dpi: Dpi,
+    /// Brightness of effects without explicit brightness
+    default_brightness: Brightness,
+    /// Acknowledgement read from device reports whether command was accepted
+    meaningful_ack: bool,
+    /// Device is connected through a wireless receiver, so reports are routed to the
+    /// paired device index instead of the receiver itself
+    via_receiver: bool,
+    /// Timeout of each USB transfer
+    usb_timeout: Duration,
+    /// Device answers reports on the interrupt endpoint; write-only devices would time out
+    expects_ack: bool,
+    /// Speeds out of range are clamped with a warning instead of rejecting the command
+    ///
+    /// Only for devices whose range is narrower than the speeds users may pass.
+    clamp_speed: bool,
+    /// USB interface receiving lighting reports
+    interface: u8,
+    /// Interrupt-in endpoint delivering acknowledgements
+    endpoint_address: u8,
 }
 
 impl DeviceDescription {
     fn get_speed(&self, speed: Option<Speed>) -> CommandResult<Speed> {
         if let Some(speed) = speed {
+            if self.clamp_speed && (speed < self.min_speed || speed > self.max_speed) {
+                let clamped = speed.clamp(self.min_speed, self.max_speed);
+                warn!(
+                    "Speed {} out of range for {}, using {}",
+                    speed.0, self.name, clamped.0
+                );
+                return Ok(clamped);
+            }
             if speed < self.min_speed {
                 return Err(CommandError::InvalidArgument(
                     "speed",
@@ -44,14 +100,39 @@ impl DeviceDescription {
         Ok(speed.unwrap_or(self.default_speed))
     }
 
-    #[allow(unused)]
+    /// Error for command the device does not support
+    fn unsupported(&self, cmd: &Command) -> CommandError {
+        CommandError::Unsupported {
+            model: self.name,
+            effect: cmd.name(),
+        }
+    }
+
+    /// Check sector of single zone device, where sector 0 is the only zone
+    fn check_single_zone(&self, sector: Option<u8>) -> CommandResult<()> {
+        match sector {
+            Some(sector) if sector > 0 => Err(CommandError::InvalidArgument(
+                "sector",
+                format!("{sector} > 0"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
     fn check_dpi(&self, dpi: Dpi) -> CommandResult<()> {
-        assert_ne!(self.min_dpi.0, u16::MAX);
-        if dpi < self.min_dpi {
+        // Devices without sensor have an empty DPI range
+        if self.min_dpi > self.max_dpi {
+            Err(self.unsupported(&Command::Dpi(dpi)))
+        } else if dpi < self.min_dpi {
             Err(CommandError::InvalidArgument(
-                "speed",
+                "dpi",
                 format!("{} < {}", dpi.0, self.min_dpi.0),
             ))
+        } else if dpi > self.max_dpi {
+            Err(CommandError::InvalidArgument(
+                "dpi",
+                format!("{} > {}", dpi.0, self.max_dpi.0),
+            ))
         } else {
             Ok(())
         }
@@ -63,6 +144,7 @@ struct GUsbDriver {
     serial_number: String,
     handle: DeviceHandle<Context>,
     description: &'static DeviceDescription,
+    last_command: Option<Command>,
 }
 
 impl GUsbDriver {
@@ -95,18 +177,159 @@ impl GUsbDriver {
                 .read_serial_number_string_ascii(&descriptor)
                 .context("reading serial number")?,
             handle,
+            last_command: None,
         })
     }
 
     fn open_interface(&mut self) -> CommandResult<GInterface<'_>> {
-        let handle = DetachedHandle::new(&mut self.handle, INTERFACE)
+        let handle = DetachedHandle::new(&mut self.handle, self.description.interface)
             .context("detaching USB device from kernel")?;
         Ok(GInterface {
             handle,
             description: self.description,
+            serial_number: &self.serial_number,
         })
     }
 
+    /// Reset device and send command
+    ///
+    /// `SetBrightness` is sent as the last lighting command with the new brightness. Raw
+    /// feature reports are sent without reset.
+    ///
+    /// Reset clears the lighting of the device. So when sending the command fails afterwards,
+    /// the last successful lighting command or the default color is restored to not leave
+    /// the device dark.
+    fn send_with_recovery(
+        &mut self,
+        reset: &[u8],
+        cmd: Command,
+        default_color: RgbColor,
+        send: impl Fn(&dyn CommandSink, Command) -> CommandResult<()>,
+    ) -> CommandResult<()> {
+        self.send_batch_with_recovery(reset, &[cmd], default_color, send)
+    }
+
+    /// Reset device once and send all commands
+    ///
+    /// Stops at the first failing command and restores lighting like `send_with_recovery`.
+    fn send_batch_with_recovery(
+        &mut self,
+        reset: &[u8],
+        cmds: &[Command],
+        default_color: RgbColor,
+        send: impl Fn(&dyn CommandSink, Command) -> CommandResult<()>,
+    ) -> CommandResult<()> {
+        trace!("{}: {:?}", self.serial_number, cmds);
+        let mut last_command = self.last_command.clone();
+        let cmds = cmds
+            .iter()
+            .map(|cmd| match cmd {
+                // Re-send current effect with new brightness
+                Command::SetBrightness(brightness) => last_command
+                    .as_ref()
+                    .and_then(|last_command| last_command.with_brightness(*brightness))
+                    .ok_or(CommandError::InvalidCommand),
+                cmd => Ok(cmd.clone()),
+            })
+            .collect::<CommandResult<Vec<_>>>()?;
+        let interface = self.open_interface()?;
+        let result = send_batch(
+            &interface,
+            Some(reset),
+            cmds,
+            &mut last_command,
+            default_color,
+            send,
+        );
+        drop(interface);
+        self.last_command = last_command;
+        result
+    }
+
+    /// Send static colors of a software effect frame
+    ///
+    /// The device is only reset when it does not show static colors already, so that
+    /// animations do not flicker.
+    fn send_frame(
+        &mut self,
+        reset: &[u8],
+        cmd: Command,
+        default_color: RgbColor,
+        send: impl Fn(&dyn CommandSink, Command) -> CommandResult<()>,
+    ) -> CommandResult<()> {
+        let shows_static = matches!(
+            self.last_command,
+            Some(
+                Command::ColorSector(_, _)
+                    | Command::ColorSectors(_)
+                    | Command::TripleColor(_, _, _)
+            )
+        );
+        let reset = if shows_static { None } else { Some(reset) };
+        let mut last_command = self.last_command.clone();
+        let interface = self.open_interface()?;
+        let result = send_batch(
+            &interface,
+            reset,
+            vec![cmd],
+            &mut last_command,
+            default_color,
+            send,
+        );
+        drop(interface);
+        self.last_command = last_command;
+        result
+    }
+
+    /// Set DPI of first sensor with HID++ AdjustableDPI feature
+    ///
+    /// Index of the feature is requested from the root feature first.
+    fn send_dpi(&mut self, dpi: Dpi) -> CommandResult<()> {
+        let description = self.description;
+        description.check_dpi(dpi)?;
+        let interface = self.open_interface()?;
+        let response = interface.request(&LogitechReport::for_dpi_feature().bytes)?;
+        match response.get(4) {
+            Some(&index) if index != 0 => {
+                interface.send_data(&LogitechReport::for_dpi(index, dpi).bytes)
+            }
+            _ => Err(description.unsupported(&Command::Dpi(dpi))),
+        }
+    }
+
+    /// Read feature table with HID++ FeatureSet feature
+    ///
+    /// Returns pairs of feature index and feature ID, starting with the root feature.
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        let name = self.description.name;
+        let interface = self.open_interface()?;
+        let request =
+            LogitechReport::long(0x00, 0x0a, &[(FEATURE_SET >> 8) as u8, FEATURE_SET as u8]);
+        let feature_set = match interface.request(&request.bytes)?.get(4) {
+            Some(&index) if index != 0 => index,
+            _ => {
+                return Err(CommandError::Unsupported {
+                    model: name,
+                    effect: "feature probing",
+                })
+            }
+        };
+
+        let count = interface.request(&LogitechReport::long(feature_set, 0x0a, &[]).bytes)?;
+        let count = count.get(4).copied().unwrap_or(0);
+        let mut features = vec![(0x00, 0x0000)];
+        for index in 1..=count {
+            let request = LogitechReport::long(feature_set, 0x1a, &[index]);
+            match interface.request(&request.bytes)?[..] {
+                [_, _, _, _, high, low, ..] => {
+                    features.push((index, u16::from_be_bytes([high, low])))
+                }
+                _ => debug!("Short response for feature index {}", index),
+            }
+        }
+        Ok(features)
+    }
+
     fn serial_number(&self) -> &str {
         &self.serial_number
     }
@@ -119,30 +342,851 @@ impl GUsbDriver {
 struct GInterface<'t> {
     #[allow(unused)]
     handle: DetachedHandle<'t, Context>,
-    #[allow(unused)]
     description: &'static DeviceDescription,
+    serial_number: &'t str,
 }
 
 impl<'t> GInterface<'t> {
     fn send_data(&self, data: &[u8]) -> CommandResult<()> {
+        if self.description.expects_ack {
+            self.request(data).map(|_| ())
+        } else {
+            self.write(&self.route(data))
+        }
+    }
+
+    /// Send report and return response of device
+    fn request(&self, data: &[u8]) -> CommandResult<Vec<u8>> {
+        let data = self.route(data);
+        self.write(&data)?;
+
+        let mut ack = [0u8; 20];
+        let len = self
+            .handle
+            .read_interrupt(
+                self.description.endpoint_address,
+                &mut ack,
+                self.description.usb_timeout,
+            )
+            .context("read_interrupt")?;
+        if log_enabled!(log::Level::Trace) {
+            trace!(
+                "{}: received {}",
+                self.serial_number,
+                hex::encode(&ack[..len])
+            );
+        }
+
+        if self.description.meaningful_ack {
+            check_ack(&data, &ack[..len])?;
+        }
+        Ok(ack[..len].to_vec())
+    }
+
+    /// Address report to paired device when connected through a receiver
+    ///
+    /// Effect builders address the device themselves, this covers raw reports like
+    /// `SetFeatureReport`.
+    fn route<'d>(&self, data: &'d [u8]) -> Cow<'d, [u8]> {
+        if self.description.via_receiver && data.len() > 1 {
+            let mut routed = data.to_vec();
+            routed[1] = RECEIVER_DEVICE_INDEX;
+            Cow::Owned(routed)
+        } else {
+            Cow::Borrowed(data)
+        }
+    }
+
+    fn write(&self, data: &[u8]) -> CommandResult<()> {
         debug!("Sending command");
+        if log_enabled!(log::Level::Trace) {
+            trace!("{}: sending {}", self.serial_number, hex::encode(data));
+        }
 
-        self.handle
-            .write_control(
+        retry_transient(|| {
+            self.handle.write_control(
                 REQUEST_TYPE,
                 REQUEST,
                 VALUE as u16,
-                INTERFACE as u16,
+                self.description.interface as u16,
                 data,
-                Duration::from_secs(5),
+                self.description.usb_timeout,
             )
-            .context("write_control")?;
+        })
+        .context("write_control")?;
+        Ok(())
+    }
+}
 
-        let mut dummy = [0u8; 20];
-        self.handle
-            .read_interrupt(ENDPOINT_ADDRESS, &mut dummy, Duration::from_secs(5))
-            .context("read_interrupt")?;
+/// Write path of reports to a device
+pub trait CommandSink: Send {
+    fn send(&self, data: &[u8]) -> CommandResult<()>;
+
+    /// Send raw HID++ long report for feature index
+    fn send_feature_report(&self, feature: u8, params: &[u8]) -> CommandResult<()> {
+        let mut report = [0u8; 20];
+        if params.len() > report.len() - 3 {
+            return Err(CommandError::InvalidArgument(
+                "params",
+                format!("{} bytes > {}", params.len(), report.len() - 3),
+            ));
+        }
+        report[0..3].copy_from_slice(&[0x11, 0xff, feature]);
+        report[3..3 + params.len()].copy_from_slice(params);
+        self.send(&report)
+    }
+}
+
+impl<'t> CommandSink for GInterface<'t> {
+    fn send(&self, data: &[u8]) -> CommandResult<()> {
+        self.send_data(data)
+    }
+}
+
+/// HID++ report padded to the length of a long report
+struct LogitechReport {
+    bytes: [u8; 20],
+}
+
+impl LogitechReport {
+    /// Long report for function of feature index
+    ///
+    /// `function` contains the software id in its lower nibble.
+    pub fn long(feature: u8, function: u8, params: &[u8]) -> Self {
+        Self::long_to(0xff, feature, function, params)
+    }
+
+    /// Long report for function of feature index of device at `device_index`
+    ///
+    /// Devices behind a receiver are addressed by their index instead of `0xff`.
+    pub fn long_to(device_index: u8, feature: u8, function: u8, params: &[u8]) -> Self {
+        Self::with_header(0x11, device_index, feature, function, params)
+    }
+
+    /// Request index of AdjustableDPI feature from root feature
+    pub fn for_dpi_feature() -> Self {
+        Self::long(
+            0x00,
+            0x0a,
+            &[(ADJUSTABLE_DPI >> 8) as u8, ADJUSTABLE_DPI as u8],
+        )
+    }
+
+    /// Set DPI of first sensor with AdjustableDPI feature at `index`
+    pub fn for_dpi(index: u8, dpi: Dpi) -> Self {
+        Self::long(index, 0x3a, &[0x00, (dpi.0 >> 8) as u8, dpi.0 as u8])
+    }
+
+    /// Short report for function of feature index
+    pub fn short(feature: u8, function: u8, params: &[u8]) -> Self {
+        Self::with_header(0x10, 0xff, feature, function, params)
+    }
+
+    fn with_header(
+        report_id: u8,
+        device_index: u8,
+        feature: u8,
+        function: u8,
+        params: &[u8],
+    ) -> Self {
+        let mut bytes = [0; 20];
+        bytes[0..4].copy_from_slice(&[report_id, device_index, feature, function]);
+        bytes[4..4 + params.len()].copy_from_slice(params);
+        Self { bytes }
+    }
+}
+
+/// Reports of HID++ color LED effects feature used by the G213 family
+///
+/// Sectors are addressed as regions starting at 1, region 0 addresses all sectors.
+struct ZoneEffects {
+    /// Feature index of color LED effects
+    feature: u8,
+}
+
+impl ZoneEffects {
+    pub const fn new(feature: u8) -> Self {
+        Self { feature }
+    }
+
+    pub fn for_color(&self, color: RgbColor) -> LogitechReport {
+        self.for_region(0, color)
+    }
+
+    pub fn for_region_color(&self, region: u8, color: RgbColor) -> LogitechReport {
+        self.for_region(region + 1, color)
+    }
+
+    fn for_region(&self, region: u8, color: RgbColor) -> LogitechReport {
+        LogitechReport::long(
+            self.feature,
+            0x3a,
+            &[region, 0x01, color.red(), color.green(), color.blue(), 0x02],
+        )
+    }
+
+    pub fn for_reset(&self) -> LogitechReport {
+        LogitechReport::long(self.feature, 0x0d, &[])
+    }
+
+    pub fn for_breathe(
+        &self,
+        color: RgbColor,
+        speed: Speed,
+        brightness: Brightness,
+    ) -> LogitechReport {
+        LogitechReport::long(
+            self.feature,
+            0x3a,
+            &[
+                0,
+                0x02,
+                color.red(),
+                color.green(),
+                color.blue(),
+                (speed.0 >> 8) as u8,
+                speed.0 as u8,
+                0,
+                brightness.0,
+            ],
+        )
+    }
+
+    pub fn for_cycle(&self, speed: Speed, brightness: Brightness) -> LogitechReport {
+        LogitechReport::long(
+            self.feature,
+            0x3a,
+            &[
+                0,
+                0x03,
+                0xff,
+                0xff,
+                0xff,
+                0,
+                0,
+                (speed.0 >> 8) as u8,
+                speed.0 as u8,
+                brightness.0,
+            ],
+        )
+    }
+
+    pub fn for_wave(
+        &self,
+        direction: Direction,
+        speed: Speed,
+        brightness: Brightness,
+    ) -> LogitechReport {
+        LogitechReport::long(
+            self.feature,
+            0x3a,
+            &[
+                0,
+                0x04,
+                0x00,
+                0x00,
+                0x00,
+                0,
+                0,
+                0,
+                speed.0 as u8,
+                direction as u8,
+                brightness.0,
+                (speed.0 >> 8) as u8,
+            ],
+        )
+    }
+
+    pub fn for_reactive(&self, color: RgbColor, speed: Speed) -> LogitechReport {
+        LogitechReport::long(
+            self.feature,
+            0x3a,
+            &[
+                0,
+                0x09,
+                color.red(),
+                color.green(),
+                color.blue(),
+                (speed.0 >> 8) as u8,
+                speed.0 as u8,
+            ],
+        )
+    }
+
+    pub fn for_start_effect(&self, state: bool) -> LogitechReport {
+        LogitechReport::long(self.feature, 0x5d, &[0x00, 0x01, if state { 1 } else { 2 }])
+    }
+}
+
+/// Send command with reports of the G213 family
+fn send_zone_effect(
+    sink: &dyn CommandSink,
+    description: &DeviceDescription,
+    effects: &ZoneEffects,
+    sectors: u8,
+    cmd: Command,
+) -> CommandResult<()> {
+    use Command::*;
+
+    match cmd {
+        ColorSector(rgb, sector) => {
+            if let Some(sector) = sector {
+                if sector >= sectors {
+                    return Err(CommandError::InvalidArgument(
+                        "sector",
+                        format!("{sector} > {}", sectors - 1),
+                    ));
+                }
+                sink.send(&effects.for_region_color(sector, rgb).bytes)
+            } else {
+                sink.send(&effects.for_color(rgb).bytes)
+            }
+        }
+        ColorSectors(colors) => {
+            for (sector, rgb) in colors.into_iter().enumerate() {
+                sink.send(&effects.for_region_color(sector as u8, rgb).bytes)?;
+            }
+            Ok(())
+        }
+        Breathe(rgb, speed, brightness) => sink.send(
+            &effects
+                .for_breathe(
+                    rgb,
+                    description.get_speed(speed)?,
+                    brightness.unwrap_or(description.default_brightness),
+                )
+                .bytes,
+        ),
+        Cycle(speed, brightness) => sink.send(
+            &effects
+                .for_cycle(
+                    description.get_speed(speed)?,
+                    brightness.unwrap_or(description.default_brightness),
+                )
+                .bytes,
+        ),
+        Wave(direction, speed, brightness) => sink.send(
+            &effects
+                .for_wave(
+                    direction,
+                    description.get_speed(speed)?,
+                    brightness.unwrap_or(description.default_brightness),
+                )
+                .bytes,
+        ),
+        Reactive(rgb, speed) => sink.send(
+            &effects
+                .for_reactive(rgb, description.get_speed(Some(speed))?)
+                .bytes,
+        ),
+        StartEffect(state) => sink.send(&effects.for_start_effect(state).bytes),
+        SetFeatureReport(feature, params) => sink.send_feature_report(feature, &params),
+        cmd => Err(description.unsupported(&cmd)),
+    }
+}
+
+/// Reports of HID++ lighting effects feature used by the G910 family of keyboards
+///
+/// Zones are 0 for keys and 1 for the logo, 0xff addresses all zones.
+struct KeyboardEffects {
+    /// Feature index of lighting effects
+    feature: u8,
+    /// HID++ device index, which is not `0xff` for devices behind a receiver
+    device_index: u8,
+}
+
+impl KeyboardEffects {
+    pub const fn new(feature: u8, device_index: u8) -> Self {
+        Self {
+            feature,
+            device_index,
+        }
+    }
+
+    fn report(&self, function: u8, params: &[u8]) -> LogitechReport {
+        LogitechReport::long_to(self.device_index, self.feature, function, params)
+    }
+
+    pub fn for_color(&self, color: RgbColor) -> LogitechReport {
+        self.for_region_color(0xff, color)
+    }
+
+    pub fn for_region_color(&self, region: u8, color: RgbColor) -> LogitechReport {
+        self.report(
+            0x3c,
+            &[region, 0x01, color.red(), color.green(), color.blue(), 0x02],
+        )
+    }
+
+    pub fn for_reset(&self) -> LogitechReport {
+        self.report(0x0d, &[])
+    }
+
+    pub fn for_breathe(
+        &self,
+        color: RgbColor,
+        speed: Speed,
+        brightness: Brightness,
+    ) -> LogitechReport {
+        self.report(
+            0x3c,
+            &[
+                0xff,
+                0x02,
+                color.red(),
+                color.green(),
+                color.blue(),
+                (speed.0 >> 8) as u8,
+                speed.0 as u8,
+                0,
+                brightness.0,
+            ],
+        )
+    }
+
+    pub fn for_cycle(&self, speed: Speed, brightness: Brightness) -> LogitechReport {
+        self.report(
+            0x3c,
+            &[
+                0xff,
+                0x03,
+                0xff,
+                0xff,
+                0xff,
+                0,
+                0,
+                (speed.0 >> 8) as u8,
+                speed.0 as u8,
+                brightness.0,
+            ],
+        )
+    }
+
+    /// Wave over keys (zone 0), the logo shows no wave
+    pub fn for_wave(
+        &self,
+        direction: Direction,
+        speed: Speed,
+        brightness: Brightness,
+    ) -> LogitechReport {
+        self.report(
+            0x3c,
+            &[
+                0x00,
+                0x04,
+                0x00,
+                0x00,
+                0x00,
+                0,
+                0,
+                0,
+                speed.0 as u8,
+                direction as u8,
+                brightness.0,
+                (speed.0 >> 8) as u8,
+            ],
+        )
+    }
+
+    pub fn for_start_effect(&self, state: bool) -> LogitechReport {
+        self.report(0x5e, &[0x00, 0x01, if state { 1 } else { 2 }])
+    }
+}
+
+/// Send command with reports of the G910 family
+fn send_keyboard_effect(
+    sink: &dyn CommandSink,
+    description: &DeviceDescription,
+    effects: &KeyboardEffects,
+    cmd: Command,
+) -> CommandResult<()> {
+    use Command::*;
+
+    match cmd {
+        ColorSector(rgb, sector) => {
+            if let Some(sector) = sector {
+                if sector > 1 {
+                    return Err(CommandError::InvalidArgument(
+                        "sector",
+                        format!("{sector} > 1"),
+                    ));
+                }
+                sink.send(&effects.for_region_color(sector, rgb).bytes)
+            } else {
+                sink.send(&effects.for_color(rgb).bytes)
+            }
+        }
+        ColorSectors(colors) => {
+            for (sector, rgb) in colors.into_iter().enumerate() {
+                sink.send(&effects.for_region_color(sector as u8, rgb).bytes)?;
+            }
+            Ok(())
+        }
+        Breathe(rgb, speed, brightness) => sink.send(
+            &effects
+                .for_breathe(
+                    rgb,
+                    description.get_speed(speed)?,
+                    brightness.unwrap_or(description.default_brightness),
+                )
+                .bytes,
+        ),
+        Cycle(speed, brightness) => sink.send(
+            &effects
+                .for_cycle(
+                    description.get_speed(speed)?,
+                    brightness.unwrap_or(description.default_brightness),
+                )
+                .bytes,
+        ),
+        Wave(direction, speed, brightness) => sink.send(
+            &effects
+                .for_wave(
+                    direction,
+                    description.get_speed(speed)?,
+                    brightness.unwrap_or(description.default_brightness),
+                )
+                .bytes,
+        ),
+        StartEffect(state) => sink.send(&effects.for_start_effect(state).bytes),
+        SetFeatureReport(feature, params) => sink.send_feature_report(feature, &params),
+        cmd => Err(description.unsupported(&cmd)),
+    }
+}
+
+/// Reset device if `reset` is given and send commands, restoring lighting when a command
+/// fails
+///
+/// `last_command` is the lighting shown before and is updated with each lighting command
+/// that was sent. On failure it is restored, or the default color if the device showed no
+/// known lighting.
+///
+/// Raw feature reports are sent last and without reset, because they are independent of
+/// lighting.
+fn send_batch(
+    sink: &dyn CommandSink,
+    reset: Option<&[u8]>,
+    cmds: Vec<Command>,
+    last_command: &mut Option<Command>,
+    default_color: RgbColor,
+    send: impl Fn(&dyn CommandSink, Command) -> CommandResult<()>,
+) -> CommandResult<()> {
+    let (raw, cmds): (Vec<Command>, Vec<Command>) = cmds
+        .into_iter()
+        .partition(|cmd| matches!(cmd, Command::SetFeatureReport(_, _)));
+    if let Some(reset) = reset.filter(|_| !cmds.is_empty()) {
+        sink.send(reset)?;
+    }
+    for cmd in cmds.into_iter().map(without_off) {
+        let is_lighting = cmd.is_lighting();
+        if let Err(err) = send(sink, cmd.clone()) {
+            let fallback = last_command
+                .clone()
+                .unwrap_or(Command::ColorSector(default_color, None));
+            warn!("Command failed, restoring {:?}", fallback);
+            if let Err(recovery_err) = send(sink, fallback) {
+                error!("Restoring lighting failed: {:?}", recovery_err);
+            }
+            return Err(err);
+        }
+        if is_lighting {
+            *last_command = Some(cmd);
+        }
+    }
+    for cmd in raw {
+        if let Command::SetFeatureReport(feature, params) = cmd {
+            sink.send_feature_report(feature, &params)?;
+        }
+    }
+    Ok(())
+}
 
+/// Show `Off` as black, devices have no report to turn lighting off
+fn without_off(cmd: Command) -> Command {
+    match cmd {
+        Command::Off => Command::ColorSector(RgbColor(0, 0, 0), None),
+        cmd => cmd,
+    }
+}
+
+/// Send reset and command to sink of dry run
+fn dry_run_effect(
+    sink: &dyn CommandSink,
+    reset: &[u8],
+    cmd: Command,
+    send: impl Fn(&dyn CommandSink, Command) -> CommandResult<()>,
+) -> CommandResult<()> {
+    sink.send(reset)?;
+    send(sink, without_off(cmd))
+}
+
+/// Sink logging reports instead of sending them
+pub(crate) struct DryRunSink;
+
+impl CommandSink for DryRunSink {
+    fn send(&self, data: &[u8]) -> CommandResult<()> {
+        info!("Dry run: {}", hex::encode(data));
         Ok(())
     }
 }
+
+/// Sink recording reports for tests of their encoding
+///
+/// Clones share the recorded reports.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct RecordingSink {
+    reports: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+#[cfg(test)]
+impl RecordingSink {
+    /// Reports sent so far
+    pub fn reports(&self) -> Vec<Vec<u8>> {
+        self.reports.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl CommandSink for RecordingSink {
+    fn send(&self, data: &[u8]) -> CommandResult<()> {
+        self.reports.lock().unwrap().push(data.to_vec());
+        Ok(())
+    }
+}
+
+/// Pad start of report to the length of a long report
+#[cfg(test)]
+pub(crate) fn padded(start: &[u8]) -> Vec<u8> {
+    let mut report = start.to_vec();
+    report.resize(20, 0);
+    report
+}
+
+/// Retry USB transfer on transient errors like after wake-up from suspend
+fn retry_transient<T>(mut transfer: impl FnMut() -> rusb::Result<T>) -> rusb::Result<T> {
+    let mut attempt = 0;
+    loop {
+        match transfer() {
+            Err(err @ (rusb::Error::Pipe | rusb::Error::Busy)) if attempt < MAX_RETRIES => {
+                attempt += 1;
+                debug!("USB transfer failed with {}, retry {}", err, attempt);
+                thread::sleep(RETRY_BACKOFF * attempt);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Check HID++ acknowledgement for error reports
+fn check_ack(request: &[u8], ack: &[u8]) -> CommandResult<()> {
+    match ack {
+        // HID++ 2.0 error: 0x11 <device> 0xff <feature index> <function> <error code>
+        [0x10 | 0x11, _, 0xff, feature, _, code, ..] if *feature == request[2] => {
+            Err(CommandError::Rejected(*code))
+        }
+        // HID++ 1.0 error: 0x10 <device> 0x8f <sub id> <address> <error code>
+        [0x10, _, 0x8f, sub_id, _, code, ..] if *sub_id == request[2] => {
+            Err(CommandError::Rejected(*code))
+        }
+        [_, _, feature, function, ..] if *feature == request[2] && *function == request[3] => {
+            Ok(())
+        }
+        _ => {
+            debug!("Unknown acknowledgement: {}", hex::encode(ack));
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GDeviceDriver;
+
+    /// Send first color channel as report and fail for `StartEffect`
+    fn send_red(sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+        match cmd {
+            Command::ColorSector(color, _) => sink.send(&[color.red()]),
+            _ => Err(CommandError::InvalidCommand),
+        }
+    }
+
+    #[test]
+    fn failing_command_restores_lighting_of_batch() {
+        let sink = RecordingSink::default();
+        let mut last_command = Some(Command::ColorSector(RgbColor(1, 0, 0), None));
+        let result = send_batch(
+            &sink,
+            Some(&[0xff]),
+            vec![
+                Command::ColorSector(RgbColor(2, 0, 0), None),
+                Command::StartEffect(true),
+            ],
+            &mut last_command,
+            RgbColor(3, 0, 0),
+            send_red,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(sink.reports(), vec![vec![0xff], vec![2], vec![2]]);
+        assert!(matches!(
+            last_command,
+            Some(Command::ColorSector(RgbColor(2, 0, 0), None))
+        ));
+    }
+
+    #[test]
+    fn failing_command_restores_previous_lighting() {
+        let sink = RecordingSink::default();
+        let mut last_command = Some(Command::ColorSector(RgbColor(1, 0, 0), None));
+        let result = send_batch(
+            &sink,
+            Some(&[0xff]),
+            vec![Command::StartEffect(true)],
+            &mut last_command,
+            RgbColor(3, 0, 0),
+            send_red,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(sink.reports(), vec![vec![0xff], vec![1]]);
+    }
+
+    #[test]
+    fn long_report() {
+        assert_eq!(
+            LogitechReport::long(0x0c, 0x3a, &[0x01, 0x02])
+                .bytes
+                .to_vec(),
+            padded(&[0x11, 0xff, 0x0c, 0x3a, 0x01, 0x02])
+        );
+    }
+
+    #[test]
+    fn short_report() {
+        assert_eq!(
+            LogitechReport::short(0x0e, 0x5b, &[0x01]).bytes.to_vec(),
+            padded(&[0x10, 0xff, 0x0e, 0x5b, 0x01])
+        );
+    }
+
+    #[test]
+    fn zone_effects_use_feature_index() {
+        assert_eq!(
+            ZoneEffects::new(0x0e)
+                .for_region_color(0, RgbColor(1, 2, 3))
+                .bytes
+                .to_vec(),
+            padded(&[0x11, 0xff, 0x0e, 0x3a, 0x01, 0x01, 0x01, 0x02, 0x03, 0x02])
+        );
+    }
+
+    #[test]
+    fn frame_is_sent_without_reset() {
+        let sink = RecordingSink::default();
+        let mut last_command = Some(Command::ColorSector(RgbColor(1, 0, 0), None));
+        send_batch(
+            &sink,
+            None,
+            vec![Command::ColorSector(RgbColor(2, 0, 0), None)],
+            &mut last_command,
+            RgbColor(3, 0, 0),
+            send_red,
+        )
+        .unwrap();
+
+        assert_eq!(sink.reports(), vec![vec![2]]);
+    }
+
+    #[test]
+    fn feature_report_is_sent_without_reset() {
+        let sink = RecordingSink::default();
+        send_batch(
+            &sink,
+            Some(&[0xff]),
+            vec![Command::SetFeatureReport(0x0b, vec![0x1c, 0x01])],
+            &mut None,
+            RgbColor(3, 0, 0),
+            send_red,
+        )
+        .unwrap();
+
+        assert_eq!(
+            sink.reports(),
+            vec![padded(&[0x11, 0xff, 0x0b, 0x1c, 0x01])]
+        );
+    }
+
+    #[test]
+    fn feature_report_is_sent_after_lighting() {
+        let sink = RecordingSink::default();
+        send_batch(
+            &sink,
+            Some(&[0xff]),
+            vec![
+                Command::SetFeatureReport(0x0b, vec![0x1c]),
+                Command::ColorSector(RgbColor(2, 0, 0), None),
+            ],
+            &mut None,
+            RgbColor(3, 0, 0),
+            send_red,
+        )
+        .unwrap();
+
+        assert_eq!(
+            sink.reports(),
+            vec![vec![0xff], vec![2], padded(&[0x11, 0xff, 0x0b, 0x1c])]
+        );
+    }
+
+    #[test]
+    fn failing_command_restores_default_color() {
+        let sink = RecordingSink::default();
+        let result = send_batch(
+            &sink,
+            Some(&[0xff]),
+            vec![Command::StartEffect(true)],
+            &mut None,
+            RgbColor(3, 0, 0),
+            send_red,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(sink.reports(), vec![vec![0xff], vec![3]]);
+    }
+
+    #[test]
+    fn off_is_sent_as_black() {
+        let sink = RecordingSink::default();
+        let mut last_command = None;
+        send_batch(
+            &sink,
+            Some(&[0xff]),
+            vec![Command::Off],
+            &mut last_command,
+            RgbColor(3, 0, 0),
+            send_red,
+        )
+        .unwrap();
+
+        assert_eq!(sink.reports(), vec![vec![0xff], vec![0]]);
+        assert!(matches!(
+            last_command,
+            Some(Command::ColorSector(RgbColor(0, 0, 0), None))
+        ));
+    }
+
+    #[test]
+    fn dry_run_sends_off_as_black() {
+        let sink = RecordingSink::default();
+        g213::G213Driver::default()
+            .dry_run(&sink, Command::Off)
+            .unwrap();
+
+        assert_eq!(
+            sink.reports()[1],
+            padded(&[0x11, 0xff, 0x0c, 0x3a, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02])
+        );
+    }
+}
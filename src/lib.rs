@@ -3,29 +3,58 @@ extern crate log;
 #[macro_use]
 extern crate quick_error;
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::env;
 use std::fmt;
 use std::fmt::Display;
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
 use hex::FromHexError;
+use ini::Properties;
 use quick_error::ResultExt;
 use rusb::{Context, Device, Hotplug, HotplugBuilder, Registration, UsbContext};
 
 use crate::config::Config;
 use crate::drivers::g203_lightsync::G203LightsyncDriver;
+use crate::drivers::g203_prodigy::G203ProdigyDriver;
 use crate::drivers::g213::G213Driver;
+use crate::drivers::g413::G413Driver;
+use crate::drivers::g502_hero::G502HeroDriver;
+use crate::drivers::g600::G600Driver;
+use crate::drivers::g910::G910Driver;
+use crate::drivers::g915::G915Driver;
+use crate::drivers::gpro::GProDriver;
+use crate::drivers::{CommandSink, DryRunSink};
 
 pub mod config;
 pub mod drivers;
 pub mod usb_ext;
 
 const LOGITECH_USB_VENDOR_ID: u16 = 0x046d;
+/// Environment variable to log reports instead of sending them to devices
+const DRY_RUN_ENV: &str = "GDEVD_DRY_RUN";
+/// Time between color steps of fades
+const FADE_STEP: Duration = Duration::from_millis(50);
+/// Longest fade of static colors, longer fades are shortened
+const MAX_FADE_DURATION: Duration = Duration::from_secs(10);
+/// Time each sector of the test pattern is shown before the next one lights up
+const TEST_PATTERN_STEP: Duration = Duration::from_millis(1000);
+const TEST_PATTERN_COLORS: [RgbColor; 6] = [
+    RgbColor(0xff, 0x00, 0x00),
+    RgbColor(0x00, 0xff, 0x00),
+    RgbColor(0x00, 0x00, 0xff),
+    RgbColor(0xff, 0xff, 0x00),
+    RgbColor(0xff, 0x00, 0xff),
+    RgbColor(0x00, 0xff, 0xff),
+];
 
 /// RGB color
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct RgbColor(pub u8, pub u8, pub u8);
 
 impl RgbColor {
@@ -44,9 +73,15 @@ impl RgbColor {
         self.2
     }
 
+    /// Parse `rrggbb` or shorthand `rgb` hex string
     pub fn from_hex(rgb_hex: &str) -> Result<Self, FromHexError> {
         let mut bytes = [0u8; 3];
-        hex::decode_to_slice(rgb_hex, &mut bytes as &mut [u8])?;
+        if rgb_hex.len() == 3 {
+            let expanded: String = rgb_hex.chars().flat_map(|c| [c, c]).collect();
+            hex::decode_to_slice(expanded, &mut bytes as &mut [u8])?;
+        } else {
+            hex::decode_to_slice(rgb_hex, &mut bytes as &mut [u8])?;
+        }
         Ok(RgbColor(bytes[0], bytes[1], bytes[2]))
     }
 
@@ -54,10 +89,177 @@ impl RgbColor {
         hex::encode([self.0, self.1, self.2])
     }
 
+    /// Parse CSS color name (like `red` or `orange`) or `rrggbb` hex string
+    pub fn parse(color: &str) -> Result<Self, FromHexError> {
+        match color.to_ascii_lowercase().as_str() {
+            "black" => Ok(RgbColor(0x00, 0x00, 0x00)),
+            "white" => Ok(RgbColor(0xff, 0xff, 0xff)),
+            "red" => Ok(RgbColor(0xff, 0x00, 0x00)),
+            "lime" => Ok(RgbColor(0x00, 0xff, 0x00)),
+            "green" => Ok(RgbColor(0x00, 0x80, 0x00)),
+            "blue" => Ok(RgbColor(0x00, 0x00, 0xff)),
+            "yellow" => Ok(RgbColor(0xff, 0xff, 0x00)),
+            "cyan" | "aqua" => Ok(RgbColor(0x00, 0xff, 0xff)),
+            "magenta" | "fuchsia" => Ok(RgbColor(0xff, 0x00, 0xff)),
+            "orange" => Ok(RgbColor(0xff, 0xa5, 0x00)),
+            "purple" => Ok(RgbColor(0x80, 0x00, 0x80)),
+            "pink" => Ok(RgbColor(0xff, 0xc0, 0xcb)),
+            _ => Self::from_hex(color),
+        }
+    }
+
     #[inline]
     pub fn to_int(&self) -> u32 {
         ((self.0 as u32) << 16) | ((self.1 as u32) << 8) | (self.2 as u32)
     }
+
+    /// Dim color by brightness
+    pub fn dimmed(&self, brightness: Brightness) -> Self {
+        let dim = |channel: u8| (channel as u16 * brightness.0 as u16 / 100) as u8;
+        RgbColor(dim(self.0), dim(self.1), dim(self.2))
+    }
+
+    /// Scale all channels by a factor between 0 and 1
+    pub fn scale(&self, factor: f32) -> Self {
+        let factor = factor.clamp(0.0, 1.0);
+        let scale = |channel: u8| (channel as f32 * factor).round() as u8;
+        RgbColor(scale(self.0), scale(self.1), scale(self.2))
+    }
+
+    /// Interpolate linearly between this color (`t = 0`) and `other` (`t = 1`)
+    pub fn lerp(&self, other: &RgbColor, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+        RgbColor(
+            mix(self.0, other.0),
+            mix(self.1, other.1),
+            mix(self.2, other.2),
+        )
+    }
+
+    /// Return `steps` colors interpolated from this color to `other`
+    pub fn gradient(&self, other: &RgbColor, steps: u8) -> Vec<RgbColor> {
+        match steps {
+            0 => vec![],
+            1 => vec![self.clone()],
+            _ => (0..steps)
+                .map(|step| self.lerp(other, step as f32 / (steps - 1) as f32))
+                .collect(),
+        }
+    }
+
+    /// Apply gamma correction so that low values are perceived less bright
+    pub fn apply_gamma(&self, gamma: f32) -> Self {
+        RgbColor(
+            gamma_channel(self.0, gamma),
+            gamma_channel(self.1, gamma),
+            gamma_channel(self.2, gamma),
+        )
+    }
+
+    /// Create color from hue in degrees (`0..360`) and saturation and value (`0..=1`)
+    ///
+    /// Hue is wrapped around, saturation and value are clamped.
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+        let h = if h.is_finite() {
+            h.rem_euclid(360.0)
+        } else {
+            0.0
+        };
+        let s = s.clamp(0.0, 1.0);
+        let v = v.clamp(0.0, 1.0);
+
+        let c = v * s;
+        let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+        let m = v - c;
+        let (r, g, b) = match (h / 60.0) as u8 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let channel = |value: f32| ((value + m) * 255.0).round() as u8;
+        RgbColor(channel(r), channel(g), channel(b))
+    }
+
+    /// Convert color to hue in degrees (`0..360`) and saturation and value (`0..=1`)
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let r = self.0 as f32 / 255.0;
+        let g = self.1 as f32 / 255.0;
+        let b = self.2 as f32 / 255.0;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let h = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let s = if max == 0.0 { 0.0 } else { delta / max };
+        (h, s, max)
+    }
+}
+
+fn gamma_channel(channel: u8, gamma: f32) -> u8 {
+    ((channel as f32 / 255.0).powf(gamma) * 255.0).round() as u8
+}
+
+/// RGB color with additional white channel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RgbwColor(pub u8, pub u8, pub u8, pub u8);
+
+impl RgbwColor {
+    #[inline]
+    pub fn white(&self) -> u8 {
+        self.3
+    }
+
+    /// Color without white channel
+    #[inline]
+    pub fn rgb(&self) -> RgbColor {
+        RgbColor(self.0, self.1, self.2)
+    }
+
+    /// Parse `rrggbbww` or `rrggbb` (without white) hex string
+    pub fn from_hex(rgbw_hex: &str) -> Result<Self, FromHexError> {
+        if rgbw_hex.len() == 6 {
+            return RgbColor::from_hex(rgbw_hex).map(RgbwColor::from);
+        }
+
+        let mut bytes = [0u8; 4];
+        hex::decode_to_slice(rgbw_hex, &mut bytes as &mut [u8])?;
+        Ok(RgbwColor(bytes[0], bytes[1], bytes[2], bytes[3]))
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode([self.0, self.1, self.2, self.3])
+    }
+
+    /// Dim color by brightness
+    pub fn dimmed(&self, brightness: Brightness) -> Self {
+        let dim = |channel: u8| (channel as u16 * brightness.0 as u16 / 100) as u8;
+        RgbwColor(dim(self.0), dim(self.1), dim(self.2), dim(self.3))
+    }
+
+    /// Scale all channels by a factor between 0 and 1
+    pub fn scale(&self, factor: f32) -> Self {
+        let factor = factor.clamp(0.0, 1.0);
+        let scale = |channel: u8| (channel as f32 * factor).round() as u8;
+        RgbwColor(scale(self.0), scale(self.1), scale(self.2), scale(self.3))
+    }
+}
+
+impl From<RgbColor> for RgbwColor {
+    fn from(rgb: RgbColor) -> Self {
+        RgbwColor(rgb.0, rgb.1, rgb.2, 0)
+    }
 }
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
@@ -82,10 +284,39 @@ impl TryFrom<&str> for Direction {
     }
 }
 
+impl Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Direction::LeftToRight => "left-to-right",
+            Direction::RightToLeft => "right-to-left",
+            Direction::CenterToEdge => "center-to-edge",
+            Direction::EdgeToCenter => "edge-to-center",
+        })
+    }
+}
+
+/// Smallest animation time step in milliseconds that any device accepts
+pub const MIN_SPEED_MILLIS: u16 = 32;
+
 /// speed of effect
+///
+/// Speeds in commands and config are animation time steps in milliseconds. They are
+/// converted to the units of the device before sending.
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Eq)]
 pub struct Speed(u16);
 
+impl Speed {
+    /// Convert time step in milliseconds to device units of `model`
+    pub fn from_millis(ms: u16, model: &dyn GDeviceModel) -> Speed {
+        Speed(ms / model.speed_unit_millis())
+    }
+
+    /// Restrict speed to range from `min` to `max`
+    pub fn clamp(self, min: Speed, max: Speed) -> Speed {
+        Speed(self.0.clamp(min.0, max.0))
+    }
+}
+
 impl From<u16> for Speed {
     #[inline]
     fn from(input: u16) -> Self {
@@ -108,6 +339,13 @@ impl From<u16> for Dpi {
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Eq)]
 pub struct Brightness(u8);
 
+impl Brightness {
+    /// Scale brightness by another brightness used as percentage
+    pub fn scaled(self, factor: Brightness) -> Self {
+        Brightness((self.0 as u16 * factor.0 as u16 / 100) as u8)
+    }
+}
+
 impl Default for Brightness {
     #[inline]
     fn default() -> Self {
@@ -115,31 +353,291 @@ impl Default for Brightness {
     }
 }
 
+quick_error! {
+    #[derive(Debug)]
+    pub enum BrightnessError {
+        OutOfRange(value: u8) {
+            display("brightness {} is not between 0 and 100", value)
+        }
+    }
+}
+
 impl TryFrom<u8> for Brightness {
-    type Error = CommandError;
+    type Error = BrightnessError;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
         if value <= 100 {
             Ok(Brightness(value))
         } else {
-            Err(CommandError::InvalidArgument(
-                "brightness",
-                format!("{} < {}", value, 100),
-            ))
+            Err(BrightnessError::OutOfRange(value))
         }
     }
 }
 
 /// command to send to device to change color
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Command {
     ColorSector(RgbColor, Option<u8>),
+    /// Color with white channel, sent as `ColorSector` to devices without white LEDs
+    ColorSectorRgbw(RgbwColor, Option<u8>),
+    /// Colors of all sectors at once
+    ColorSectors(Vec<RgbColor>),
+    /// Fixed colors of left, middle and right zone of devices with three zones
+    TripleColor(RgbColor, RgbColor, RgbColor),
+    /// Colors interpolated from first to last sector
+    Gradient(RgbColor, RgbColor),
     Breathe(RgbColor, Option<Speed>, Option<Brightness>),
     Cycle(Option<Speed>, Option<Brightness>),
     Wave(Direction, Option<Speed>, Option<Brightness>),
     Blend(Option<Speed>, Option<Brightness>),
+    /// Light up keys when pressed
+    Reactive(RgbColor, Speed),
+    /// Change brightness of current effect without changing the effect
+    SetBrightness(Brightness),
+    /// Turn lighting off
+    Off,
+    /// Light sectors one after another in distinct colors to show their position
+    ///
+    /// Saved lighting is restored afterwards.
+    TestPattern,
     StartEffect(bool),
     Dpi(Dpi),
+    /// Set DPI values of the stages the DPI button switches through
+    DpiStages(Vec<Dpi>),
+    /// Select DPI stage by index
+    DpiStage(u8),
+    SelectOnboardProfile(u8),
+    /// Raw HID++ report for feature index with function byte and parameters
+    ///
+    /// Only sent when `allow_raw` is enabled in config. The bytes are not
+    /// validated and can put the device into an unexpected state until it is
+    /// replugged. Raw reports are never saved to config.
+    SetFeatureReport(u8, Vec<u8>),
+}
+
+impl Command {
+    /// Return name of command for messages
+    pub fn name(&self) -> &'static str {
+        use Command::*;
+
+        match self {
+            ColorSector(_, _) => "ColorSector",
+            ColorSectorRgbw(_, _) => "ColorSectorRgbw",
+            ColorSectors(_) => "ColorSectors",
+            TripleColor(_, _, _) => "TripleColor",
+            Gradient(_, _) => "Gradient",
+            Breathe(_, _, _) => "Breathe",
+            Cycle(_, _) => "Cycle",
+            Wave(_, _, _) => "Wave",
+            Blend(_, _) => "Blend",
+            Reactive(_, _) => "Reactive",
+            SetBrightness(_) => "SetBrightness",
+            Off => "Off",
+            TestPattern => "TestPattern",
+            StartEffect(_) => "StartEffect",
+            Dpi(_) => "Dpi",
+            DpiStages(_) => "DpiStages",
+            DpiStage(_) => "DpiStage",
+            SelectOnboardProfile(_) => "SelectOnboardProfile",
+            SetFeatureReport(_, _) => "SetFeatureReport",
+        }
+    }
+
+    /// Return command with its brightness scaled by `factor`
+    ///
+    /// Static colors are dimmed directly because they have no brightness.
+    pub fn dimmed(&self, factor: Brightness) -> Command {
+        use Command::*;
+
+        let scale =
+            |brightness: Option<Brightness>| Some(brightness.unwrap_or_default().scaled(factor));
+        match self {
+            ColorSector(color, sector) => ColorSector(color.dimmed(factor), *sector),
+            ColorSectorRgbw(color, sector) => ColorSectorRgbw(color.dimmed(factor), *sector),
+            ColorSectors(colors) => {
+                ColorSectors(colors.iter().map(|color| color.dimmed(factor)).collect())
+            }
+            TripleColor(left, middle, right) => TripleColor(
+                left.dimmed(factor),
+                middle.dimmed(factor),
+                right.dimmed(factor),
+            ),
+            Gradient(start, end) => Gradient(start.dimmed(factor), end.dimmed(factor)),
+            Breathe(color, speed, brightness) => Breathe(color.clone(), *speed, scale(*brightness)),
+            Cycle(speed, brightness) => Cycle(*speed, scale(*brightness)),
+            Wave(direction, speed, brightness) => Wave(*direction, *speed, scale(*brightness)),
+            Blend(speed, brightness) => Blend(*speed, scale(*brightness)),
+            Reactive(color, speed) => Reactive(color.dimmed(factor), *speed),
+            SetBrightness(brightness) => SetBrightness(brightness.scaled(factor)),
+            Off
+            | TestPattern
+            | StartEffect(_)
+            | Dpi(_)
+            | DpiStages(_)
+            | DpiStage(_)
+            | SelectOnboardProfile(_)
+            | SetFeatureReport(_, _) => self.clone(),
+        }
+    }
+
+    /// Return command with gamma correction applied to its colors
+    pub fn gamma_corrected(&self, gamma: f32) -> Command {
+        use Command::*;
+
+        match self {
+            ColorSector(color, sector) => ColorSector(color.apply_gamma(gamma), *sector),
+            ColorSectorRgbw(color, sector) => {
+                let rgb = color.rgb().apply_gamma(gamma);
+                ColorSectorRgbw(
+                    RgbwColor(rgb.0, rgb.1, rgb.2, gamma_channel(color.white(), gamma)),
+                    *sector,
+                )
+            }
+            Breathe(color, speed, brightness) => {
+                Breathe(color.apply_gamma(gamma), *speed, *brightness)
+            }
+            ColorSectors(colors) => ColorSectors(
+                colors
+                    .iter()
+                    .map(|color| color.apply_gamma(gamma))
+                    .collect(),
+            ),
+            TripleColor(left, middle, right) => TripleColor(
+                left.apply_gamma(gamma),
+                middle.apply_gamma(gamma),
+                right.apply_gamma(gamma),
+            ),
+            Gradient(start, end) => Gradient(start.apply_gamma(gamma), end.apply_gamma(gamma)),
+            Reactive(color, speed) => Reactive(color.apply_gamma(gamma), *speed),
+            _ => self.clone(),
+        }
+    }
+
+    /// Return command adapted to `model`
+    ///
+    /// Gradients are split into sector colors, white channels are dropped for RGB models,
+    /// sectors the model does not have color all sectors and speeds are converted from
+    /// milliseconds to device units.
+    pub fn adapted_to(&self, model: &dyn GDeviceModel) -> Command {
+        use Command::*;
+
+        match self {
+            Gradient(start, _) if model.get_sectors() <= 1 => ColorSector(start.clone(), None),
+            Gradient(start, end) => ColorSectors(start.gradient(end, model.get_sectors())),
+            ColorSectorRgbw(color, sector) if !model.has_white_channel() => {
+                ColorSector(color.rgb(), fit_sector(model, *sector))
+            }
+            ColorSectorRgbw(color, sector) => {
+                ColorSectorRgbw(color.clone(), fit_sector(model, *sector))
+            }
+            ColorSector(color, sector) => ColorSector(color.clone(), fit_sector(model, *sector)),
+            Breathe(color, speed, brightness) => Breathe(
+                color.clone(),
+                speed.map(|speed| Speed::from_millis(speed.0, model)),
+                *brightness,
+            ),
+            Cycle(speed, brightness) => Cycle(
+                speed.map(|speed| Speed::from_millis(speed.0, model)),
+                *brightness,
+            ),
+            Wave(direction, speed, brightness) => Wave(
+                *direction,
+                speed.map(|speed| Speed::from_millis(speed.0, model)),
+                *brightness,
+            ),
+            Blend(speed, brightness) => Blend(
+                speed.map(|speed| Speed::from_millis(speed.0, model)),
+                *brightness,
+            ),
+            Reactive(color, speed) => Reactive(color.clone(), Speed::from_millis(speed.0, model)),
+            _ => self.clone(),
+        }
+    }
+
+    /// Return whether command sets the lighting of the device
+    pub fn is_lighting(&self) -> bool {
+        use Command::*;
+
+        match self {
+            ColorSector(_, _)
+            | ColorSectorRgbw(_, _)
+            | ColorSectors(_)
+            | TripleColor(_, _, _)
+            | Gradient(_, _)
+            | Breathe(_, _, _)
+            | Cycle(_, _)
+            | Wave(_, _, _)
+            | Blend(_, _)
+            | Reactive(_, _)
+            | Off => true,
+            SetBrightness(_)
+            | TestPattern
+            | StartEffect(_)
+            | Dpi(_)
+            | DpiStages(_)
+            | DpiStage(_)
+            | SelectOnboardProfile(_)
+            | SetFeatureReport(_, _) => false,
+        }
+    }
+
+    /// Return command with changed brightness or `None` if command has no brightness
+    pub fn with_brightness(&self, brightness: Brightness) -> Option<Command> {
+        use Command::*;
+
+        match self {
+            Breathe(color, speed, _) => Some(Breathe(color.clone(), *speed, Some(brightness))),
+            Cycle(speed, _) => Some(Cycle(*speed, Some(brightness))),
+            Wave(direction, speed, _) => Some(Wave(*direction, *speed, Some(brightness))),
+            Blend(speed, _) => Some(Blend(*speed, Some(brightness))),
+            SetBrightness(_) => Some(SetBrightness(brightness)),
+            ColorSector(_, _)
+            | ColorSectorRgbw(_, _)
+            | ColorSectors(_)
+            | TripleColor(_, _, _)
+            | Gradient(_, _)
+            | Reactive(_, _)
+            | Off
+            | TestPattern
+            | StartEffect(_)
+            | Dpi(_)
+            | DpiStages(_)
+            | DpiStage(_)
+            | SelectOnboardProfile(_)
+            | SetFeatureReport(_, _) => None,
+        }
+    }
+}
+
+/// Kind of lighting effect
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EffectKind {
+    Static,
+    Breathe,
+    Cycle,
+    Wave,
+    Blend,
+    Reactive,
+}
+
+impl Display for EffectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EffectKind::Static => "static",
+            EffectKind::Breathe => "breathe",
+            EffectKind::Cycle => "cycle",
+            EffectKind::Wave => "wave",
+            EffectKind::Blend => "blend",
+            EffectKind::Reactive => "reactive",
+        })
+    }
+}
+
+/// Event reported by device hardware
+#[derive(Clone, Debug)]
+pub enum DeviceEvent {
+    /// Brightness was changed with keys on device
+    BrightnessChanged(Brightness),
 }
 
 pub type UsbDevice = Device<Context>;
@@ -147,6 +645,16 @@ pub type UsbDevice = Device<Context>;
 pub enum GDeviceManagerEvent {
     DevicePluggedIn(UsbDevice),
     DevicePluggedOut(UsbDevice),
+    /// System resumed from suspend
+    Resumed,
+    /// Start rainbow animated by daemon with hue period in milliseconds
+    StartRainbow(Speed),
+    /// Stop software effect and keep its last frame
+    StopAnimation,
+    /// Fade static color of sector (or all sectors) to color in duration
+    StartFade(RgbColor, Option<u8>, Duration),
+    /// Light sectors one after another to show their position
+    StartTestPattern,
     Shutdown,
 }
 
@@ -156,12 +664,50 @@ pub enum DeviceType {
     Mouse,
 }
 
-pub struct GModelId(String);
+impl Display for DeviceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DeviceType::Keyboard => "keyboard",
+            DeviceType::Mouse => "mouse",
+        })
+    }
+}
+
+pub struct GModelId(pub String);
+
+/// Maturity of a driver
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stability {
+    /// Driver is tested on real hardware
+    Stable,
+    /// Driver is untested and needs to be allowed in config
+    Experimental,
+}
+
+impl Display for Stability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stability::Stable => "stable",
+            Stability::Experimental => "experimental",
+        })
+    }
+}
 
 /// Driver for Logitech G devices
 pub trait GDeviceDriver: Send {
     fn get_model(&self) -> GDeviceModelRef;
     fn open_device(&self, device: &UsbDevice) -> Option<Box<dyn GDevice>>;
+    /// Return whether driver is tested on real hardware
+    fn stability(&self) -> Stability {
+        Stability::Experimental
+    }
+    /// Send reports of command to `sink` instead of a device
+    fn dry_run(&self, _sink: &dyn CommandSink, cmd: Command) -> CommandResult<()> {
+        Err(CommandError::Unsupported {
+            model: self.get_model().get_name(),
+            effect: cmd.name(),
+        })
+    }
 }
 
 pub type GDeviceDriverRef = Box<dyn GDeviceDriver>;
@@ -179,6 +725,156 @@ pub trait GDeviceModel: Send + Sync {
     fn get_type(&self) -> DeviceType;
 
     fn usb_product_id(&self) -> u16;
+
+    /// Lighting effects supported by model
+    fn supported_effects(&self) -> &'static [EffectKind] {
+        &[
+            EffectKind::Static,
+            EffectKind::Breathe,
+            EffectKind::Cycle,
+            EffectKind::Wave,
+        ]
+    }
+
+    /// Return whether device has dedicated white LEDs
+    fn has_white_channel(&self) -> bool {
+        false
+    }
+
+    /// Number of lighting profiles stored on device (0 if unsupported)
+    fn get_onboard_profiles(&self) -> u8 {
+        0
+    }
+
+    /// Brightness of effects without explicit brightness
+    fn default_brightness(&self) -> Brightness {
+        Brightness::default()
+    }
+
+    /// Milliseconds per unit of speeds sent to device
+    ///
+    /// Effect reports of all supported devices take periods in milliseconds.
+    fn speed_unit_millis(&self) -> u16 {
+        1
+    }
+
+    /// Maximum number of DPI stages (0 if unsupported)
+    fn num_dpi_stages(&self) -> u8 {
+        0
+    }
+
+    /// Whether sensitivity can be set with `Command::Dpi`
+    fn supports_dpi(&self) -> bool {
+        false
+    }
+
+    /// Whether three zones can be colored independently with `Command::TripleColor`
+    fn supports_triple_color(&self) -> bool {
+        false
+    }
+
+    /// Whether sectors can be colored independently with `Command::ColorSector`
+    fn supports_sector_colors(&self) -> bool {
+        self.get_sectors() > 1
+    }
+}
+
+/// Command showing one color for each sector of model
+///
+/// Returns `None` if the model cannot color its sectors independently.
+fn sector_colors_command(model: &dyn GDeviceModel, colors: &[RgbColor]) -> Option<Command> {
+    match colors {
+        [] => None,
+        [color] => Some(Command::ColorSector(color.clone(), None)),
+        _ if model.supports_sector_colors() => Some(Command::ColorSectors(colors.to_vec())),
+        [left, middle, right] if model.supports_triple_color() => Some(Command::TripleColor(
+            left.clone(),
+            middle.clone(),
+            right.clone(),
+        )),
+        _ => None,
+    }
+}
+
+/// Color all sectors instead of a sector the model does not have
+fn fit_sector(model: &dyn GDeviceModel, sector: Option<u8>) -> Option<u8> {
+    match sector {
+        Some(sector) if sector >= model.get_sectors() => {
+            warn!(
+                "Sector {} exceeds {} sectors of {}, coloring all sectors",
+                sector,
+                model.get_sectors(),
+                model.get_name()
+            );
+            None
+        }
+        _ => sector,
+    }
+}
+
+/// Check that command is supported by device model
+pub fn check_command(model: &dyn GDeviceModel, cmd: &Command) -> CommandResult<()> {
+    match cmd {
+        Command::ColorSectors(colors) if colors.len() != model.get_sectors() as usize => {
+            return Err(CommandError::InvalidArgument(
+                "colors",
+                format!("{} colors != {} sectors", colors.len(), model.get_sectors()),
+            ));
+        }
+        Command::TripleColor(_, _, _) if !model.supports_triple_color() => {
+            return Err(CommandError::Unsupported {
+                model: model.get_name(),
+                effect: cmd.name(),
+            });
+        }
+        Command::SelectOnboardProfile(slot) => {
+            let profiles = model.get_onboard_profiles();
+            if profiles == 0 {
+                return Err(CommandError::Unsupported {
+                    model: model.get_name(),
+                    effect: cmd.name(),
+                });
+            }
+            if *slot == 0 || *slot > profiles {
+                return Err(CommandError::InvalidArgument(
+                    "profile",
+                    format!("{} not in 1..={}", slot, profiles),
+                ));
+            }
+        }
+        Command::DpiStages(stages) => {
+            let max_stages = model.num_dpi_stages();
+            if max_stages == 0 {
+                return Err(CommandError::Unsupported {
+                    model: model.get_name(),
+                    effect: cmd.name(),
+                });
+            }
+            if stages.is_empty() || stages.len() > max_stages as usize {
+                return Err(CommandError::InvalidArgument(
+                    "stages",
+                    format!("{} stages not in 1..={}", stages.len(), max_stages),
+                ));
+            }
+        }
+        Command::DpiStage(stage) => {
+            let max_stages = model.num_dpi_stages();
+            if max_stages == 0 {
+                return Err(CommandError::Unsupported {
+                    model: model.get_name(),
+                    effect: cmd.name(),
+                });
+            }
+            if *stage >= max_stages {
+                return Err(CommandError::InvalidArgument(
+                    "stage",
+                    format!("{} not in 0..{}", stage, max_stages),
+                ));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
 }
 
 pub type GDeviceModelRef = Arc<dyn GDeviceModel>;
@@ -195,6 +891,80 @@ pub trait GDevice: Display + Send {
     fn get_model(&self) -> GDeviceModelRef;
     /// Send command to device
     fn send_command(&mut self, cmd: Command) -> CommandResult<()>;
+    /// Send frame of software effect with static colors
+    ///
+    /// Drivers that reset the device before each command should override this to skip the
+    /// reset while the device shows static colors, so that animations do not flicker.
+    fn send_frame(&mut self, cmd: Command) -> CommandResult<()> {
+        self.send_command(cmd)
+    }
+    /// Send multiple commands to device
+    ///
+    /// Drivers that reset the device before each command should override this to reset only
+    /// once, so that applying a config with multiple commands does not flicker.
+    fn send_command_batch(&mut self, cmds: &[Command]) -> CommandResult<()> {
+        for cmd in cmds {
+            self.send_command(cmd.clone())?;
+        }
+        Ok(())
+    }
+    /// Poll events reported by device hardware
+    ///
+    /// Drivers should report hardware state changes (like brightness keys) so that the
+    /// config stays in sync with the device. Default implementation reports nothing.
+    fn poll_events(&mut self) -> Vec<DeviceEvent> {
+        vec![]
+    }
+    /// Read lighting effects supported by device firmware
+    ///
+    /// Returns `None` if device cannot report its effects.
+    fn query_supported_effects(&mut self) -> CommandResult<Option<Vec<EffectKind>>> {
+        Ok(None)
+    }
+    /// Read HID++ feature table as pairs of feature index and feature ID
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        Err(CommandError::Unsupported {
+            model: self.get_model().get_name(),
+            effect: "feature probing",
+        })
+    }
+    /// Read color currently shown by device
+    ///
+    /// Returns `None` if device cannot report its color.
+    fn read_current_color(&self) -> CommandResult<Option<RgbColor>> {
+        Ok(None)
+    }
+    /// Return active onboard profile slot if device has onboard profiles
+    fn get_onboard_profile(&mut self) -> CommandResult<Option<u8>> {
+        Ok(None)
+    }
+    /// Return firmware version from bcdDevice of USB device descriptor
+    fn firmware_version(&self) -> Option<String> {
+        let version = self.dev().device_descriptor().ok()?.device_version();
+        Some(format!(
+            "{}.{}.{}",
+            version.major(),
+            version.minor(),
+            version.sub_minor()
+        ))
+    }
+    /// Describe device for bug reports
+    fn get_debug_info(&self) -> String {
+        let dev = self.dev();
+        let model = self.get_model();
+        format!(
+            "model={} type={:?} usb={:04x}:{:04x} firmware={} bus={} address={} sectors={} default_brightness={}",
+            model.get_name(),
+            model.get_type(),
+            LOGITECH_USB_VENDOR_ID,
+            model.usb_product_id(),
+            self.firmware_version().as_deref().unwrap_or("unknown"),
+            dev.bus_number(),
+            dev.address(),
+            model.get_sectors(),
+            model.default_brightness().0
+        )
+    }
 }
 
 pub type GDeviceRef = Box<dyn GDevice>;
@@ -202,6 +972,28 @@ pub type GDeviceRef = Box<dyn GDevice>;
 pub struct GDeviceInfo {
     pub model: &'static str,
     pub serial: String,
+    pub firmware_version: Option<String>,
+    pub device_type: DeviceType,
+}
+
+pub struct GDeviceCapabilities {
+    pub model: &'static str,
+    pub serial: String,
+    pub sectors: u8,
+    /// Names of supported effects and `dpi` for adjustable sensitivity
+    pub features: Vec<String>,
+}
+
+pub struct GDeviceState {
+    pub model: &'static str,
+    pub serial: String,
+    /// Saved effect as described by config, or color read from device without config
+    pub effect: String,
+}
+
+pub struct GDriverInfo {
+    pub model: &'static str,
+    pub stability: Stability,
 }
 
 quick_error! {
@@ -218,131 +1010,1210 @@ quick_error! {
         InvalidCommand {
             display("Invalid command")
         }
+        DeviceNotFound(serial: String) {
+            display("Device with serial number {} not found", serial)
+        }
+        Unsupported { model: &'static str, effect: &'static str } {
+            display("{} not supported by {}", effect, model)
+        }
+        Rejected(code: u8) {
+            display("Command rejected by device with error code {}", code)
+        }
+        /// Command failed for some devices and was sent to the others
+        PartialFailure(failures: Vec<(String, CommandError)>) {
+            display("Command failed for {}", failures
+                .iter()
+                .map(|(device, err)| format!("{}: {}", device, err))
+                .collect::<Vec<_>>()
+                .join(", "))
+        }
     }
 }
 
 type CommandResult<T> = Result<T, CommandError>;
 
-impl PartialEq for Box<dyn GDeviceModel> {
-    fn eq(&self, other: &Self) -> bool {
-        self.get_name() == other.get_name()
+/// Combine failures of devices into one error
+fn partial_failure(failures: Vec<(String, CommandError)>) -> CommandResult<()> {
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(CommandError::PartialFailure(failures))
     }
 }
 
-impl Eq for Box<dyn GDeviceModel> {}
+/// Drivers for all supported devices
+fn all_drivers() -> Vec<GDeviceDriverRef> {
+    vec![
+        Box::<G213Driver>::default(),
+        Box::<G203LightsyncDriver>::default(),
+        Box::<G203ProdigyDriver>::default(),
+        Box::<G413Driver>::default(),
+        Box::<G502HeroDriver>::default(),
+        Box::<G910Driver>::default(),
+        Box::<G915Driver>::default(),
+        Box::<GProDriver>::default(),
+        Box::<G600Driver>::default(),
+    ]
+}
 
-impl Hash for Box<dyn GDeviceModel> {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        state.write(self.get_name().as_bytes())
-    }
+/// Return whether driver may claim devices
+///
+/// Experimental drivers need `allow_experimental`, which defaults to the `experimental`
+/// feature.
+fn is_driver_allowed(driver: &dyn GDeviceDriver, allow_experimental: bool) -> bool {
+    allow_experimental || driver.stability() != Stability::Experimental
 }
 
-struct GDeviceManagerState {
-    pub context: Context,
-    #[allow(dead_code)]
-    hotplug: Registration<Context>,
-    config: Config,
-    devices: Vec<GDeviceRef>,
-    drivers: Vec<GDeviceDriverRef>,
+fn find_driver_for_device<'a>(
+    drivers: &'a [GDeviceDriverRef],
+    device: &UsbDevice,
+) -> Option<&'a dyn GDeviceDriver> {
+    let descriptor = device.device_descriptor().unwrap();
+    if descriptor.vendor_id() == LOGITECH_USB_VENDOR_ID {
+        drivers
+            .iter()
+            .find(|driver| descriptor.product_id() == driver.get_model().usb_product_id())
+            .map(|driver| driver.deref())
+    } else {
+        None
+    }
 }
 
-impl GDeviceManagerState {
-    pub fn new(tx: mpsc::SyncSender<GDeviceManagerEvent>) -> CommandResult<Self> {
-        let context = Context::new().context("creating USB context")?;
-        let config = Config::load();
-        Ok(Self {
-            devices: vec![],
-            config,
-            drivers: vec![
-                Box::<G213Driver>::default(),
-                Box::<G203LightsyncDriver>::default(),
-            ],
-            hotplug: HotplugBuilder::new()
-                .vendor_id(LOGITECH_USB_VENDOR_ID)
-                .register(&context, Box::new(HotPlugHandler { channel: tx }))
-                .context("registering hotplug callback")?,
-            context,
+/// Open all connected devices with an allowed driver
+///
+/// Unlike [`GDeviceManager`] no config is applied and devices plugged in later are not noticed.
+/// Commands are checked and adapted to the model like by the manager, see [`AdaptedDevice`].
+/// Experimental drivers are only used with the `experimental` feature.
+pub fn open_devices(context: &Context) -> CommandResult<Vec<GDeviceRef>> {
+    let drivers = all_drivers();
+    let usb_devices = context.devices().context("listing USB devices")?;
+    Ok(usb_devices
+        .iter()
+        .filter_map(|device| {
+            let driver = find_driver_for_device(&drivers, &device)?;
+            if !is_driver_allowed(driver, cfg!(feature = "experimental")) {
+                info!(
+                    "Skipping {} with experimental driver",
+                    driver.get_model().get_name()
+                );
+                return None;
+            }
+            driver.open_device(&device)
         })
+        .map(|device| Box::new(AdaptedDevice::new(device)) as GDeviceRef)
+        .collect())
+}
+
+/// Device that checks commands and adapts them to its model before sending
+///
+/// Commands are adapted with [`Command::adapted_to`] like the manager does, but without
+/// gamma correction and brightness override of the config.
+pub struct AdaptedDevice {
+    device: GDeviceRef,
+}
+
+impl AdaptedDevice {
+    pub fn new(device: GDeviceRef) -> Self {
+        Self { device }
     }
 
-    pub fn get_devices(&mut self) -> Vec<GDeviceInfo> {
-        self.devices
-            .iter()
-            .map(|dev| GDeviceInfo {
-                model: dev.get_model().get_name(),
-                serial: dev.serial_number().to_string(),
-            })
-            .collect()
+    fn adapt(&self, cmd: &Command) -> CommandResult<Command> {
+        let model = self.device.get_model();
+        check_command(&*model, cmd)?;
+        Ok(cmd.adapted_to(&*model))
     }
+}
 
-    pub fn get_drivers(&mut self) -> Vec<&'static str> {
-        self.drivers
-            .iter()
-            .map(|drv| drv.get_model().get_name())
-            .collect()
+impl Display for AdaptedDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.device.fmt(f)
     }
+}
 
-    pub fn load_devices(&mut self) -> CommandResult<()> {
-        info!("Scan devices");
-        let usb_devices = self.context.devices().context("listing USB devices")?;
-        self.devices = usb_devices
-            .iter()
-            .filter_map(|device| self.try_open_device(&device))
-            .collect();
-        info!("Found {} device(s)", self.devices.len());
-        self.apply_config();
-        Ok(())
+impl GDevice for AdaptedDevice {
+    fn dev(&self) -> &UsbDevice {
+        self.device.dev()
     }
 
-    fn find_driver_for_device(&self, device: &Device<Context>) -> Option<&dyn GDeviceDriver> {
-        let descriptor = device.device_descriptor().unwrap();
-        if descriptor.vendor_id() == LOGITECH_USB_VENDOR_ID {
-            self.drivers
-                .iter()
-                .find(|driver| descriptor.product_id() == driver.get_model().usb_product_id())
-                .map(|driver| driver.deref())
-        } else {
-            None
-        }
+    fn serial_number(&self) -> &str {
+        self.device.serial_number()
     }
 
-    fn try_open_device(&self, device: &UsbDevice) -> Option<Box<dyn GDevice>> {
-        if let Some(driver) = self.find_driver_for_device(device) {
-            info!("Found device {}", driver.get_model().get_name());
-            driver.open_device(device)
-        } else {
-            None
-        }
+    fn get_model(&self) -> GDeviceModelRef {
+        self.device.get_model()
     }
 
-    pub fn send_command(&mut self, cmd: Command) {
-        for device in &mut self.devices {
-            if let Err(err) = device.send_command(cmd.clone()) {
-                error!("Sending command failed for device: {:?}", err);
-            }
+    fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
+        let cmd = self.adapt(&cmd)?;
+        self.device.send_command(cmd)
+    }
+
+    fn send_frame(&mut self, cmd: Command) -> CommandResult<()> {
+        let cmd = self.adapt(&cmd)?;
+        self.device.send_frame(cmd)
+    }
+
+    fn send_command_batch(&mut self, cmds: &[Command]) -> CommandResult<()> {
+        let cmds = cmds
+            .iter()
+            .map(|cmd| self.adapt(cmd))
+            .collect::<CommandResult<Vec<_>>>()?;
+        self.device.send_command_batch(&cmds)
+    }
+
+    fn poll_events(&mut self) -> Vec<DeviceEvent> {
+        self.device.poll_events()
+    }
+
+    fn query_supported_effects(&mut self) -> CommandResult<Option<Vec<EffectKind>>> {
+        self.device.query_supported_effects()
+    }
+
+    fn probe_features(&mut self) -> CommandResult<Vec<(u8, u16)>> {
+        self.device.probe_features()
+    }
+
+    fn read_current_color(&self) -> CommandResult<Option<RgbColor>> {
+        self.device.read_current_color()
+    }
+
+    fn get_onboard_profile(&mut self) -> CommandResult<Option<u8>> {
+        self.device.get_onboard_profile()
+    }
+
+    fn firmware_version(&self) -> Option<String> {
+        self.device.firmware_version()
+    }
+
+    fn get_debug_info(&self) -> String {
+        self.device.get_debug_info()
+    }
+}
+
+impl PartialEq for Box<dyn GDeviceModel> {
+    fn eq(&self, other: &Self) -> bool {
+        self.get_name() == other.get_name()
+    }
+}
+
+impl Eq for Box<dyn GDeviceModel> {}
+
+impl Hash for Box<dyn GDeviceModel> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write(self.get_name().as_bytes())
+    }
+}
+
+struct GDeviceManagerState {
+    /// USB context, which is not needed for dry runs
+    pub context: Option<Context>,
+    #[allow(dead_code)]
+    hotplug: Option<Registration<Context>>,
+    config: Config,
+    devices: Vec<GDeviceRef>,
+    drivers: Vec<GDeviceDriverRef>,
+    brightness_override: Option<Brightness>,
+    device_watchers: Vec<mpsc::Sender<()>>,
+    /// Sink for reports of each driver when no devices are opened
+    dry_run: Option<Box<dyn CommandSink>>,
+    /// Running software effect
+    animation: Option<Animation>,
+    /// Colors shown by devices when they were opened, by serial number
+    initial_colors: HashMap<String, RgbColor>,
+    /// Lighting shown by devices that is not saved to config, by serial number
+    unsaved: HashMap<String, Command>,
+}
+
+/// Software effect animated by daemon
+enum Animation {
+    Rainbow(Rainbow),
+    Fade(Fade),
+    TestPattern(TestPattern),
+}
+
+/// Rainbow with hues offset per sector, rotating over time
+struct Rainbow {
+    period: Duration,
+    start: Instant,
+    /// Serial numbers of devices that failed a frame and are skipped from then on
+    failed: Vec<String>,
+}
+
+impl Rainbow {
+    /// Colors of sectors at current time
+    fn frame(&self, sectors: u8) -> Vec<RgbColor> {
+        let elapsed = self.start.elapsed().as_millis() % self.period.as_millis().max(1);
+        let phase = elapsed as f32 / self.period.as_millis().max(1) as f32 * 360.0;
+        (0..sectors)
+            .map(|sector| {
+                let offset = sector as f32 * 360.0 / sectors as f32;
+                RgbColor::from_hsv(phase + offset, 1.0, 1.0)
+            })
+            .collect()
+    }
+}
+
+/// Fade from saved static colors to a color, which is saved when the fade ends
+struct Fade {
+    /// Color at start of fade by serial number
+    from: Vec<(String, RgbColor)>,
+    to: RgbColor,
+    sector: Option<u8>,
+    duration: Duration,
+    start: Instant,
+}
+
+impl Fade {
+    /// Progress of fade from 0 to 1
+    fn progress(&self) -> f32 {
+        let duration = self.duration.as_secs_f32().max(f32::EPSILON);
+        (self.start.elapsed().as_secs_f32() / duration).min(1.0)
+    }
+}
+
+/// Sectors lit one after another in distinct colors, until config is restored
+struct TestPattern {
+    /// Serial numbers of devices showing the pattern
+    serials: Vec<String>,
+    /// Number of steps, one for each sector of the device with most sectors
+    steps: u8,
+    /// Last step that was shown
+    shown: Option<u8>,
+    start: Instant,
+}
+
+impl GDeviceManagerState {
+    pub fn new(
+        tx: mpsc::SyncSender<GDeviceManagerEvent>,
+        config: Config,
+        dry_run: Option<Box<dyn CommandSink>>,
+    ) -> CommandResult<Self> {
+        let (context, hotplug) = if dry_run.is_some() {
+            (None, None)
+        } else {
+            let context = Context::new().context("creating USB context")?;
+            let hotplug = HotplugBuilder::new()
+                .vendor_id(LOGITECH_USB_VENDOR_ID)
+                .register(&context, Box::new(HotPlugHandler { channel: tx }))
+                .context("registering hotplug callback")?;
+            (Some(context), Some(hotplug))
+        };
+        let state = Self {
+            dry_run,
+            devices: vec![],
+            initial_colors: HashMap::new(),
+            unsaved: HashMap::new(),
+            config,
+            drivers: all_drivers(),
+            brightness_override: None,
+            device_watchers: vec![],
+            animation: None,
+            hotplug,
+            context,
+        };
+        // log config warnings once at load
+        state.config_warnings();
+        Ok(state)
+    }
+
+    pub fn get_devices(&mut self) -> Vec<GDeviceInfo> {
+        self.devices
+            .iter()
+            .map(|dev| GDeviceInfo {
+                model: dev.get_model().get_name(),
+                serial: dev.serial_number().to_string(),
+                firmware_version: dev.firmware_version(),
+                device_type: dev.get_model().get_type(),
+            })
+            .collect()
+    }
+
+    pub fn get_drivers(&mut self) -> Vec<GDriverInfo> {
+        self.drivers
+            .iter()
+            .map(|drv| GDriverInfo {
+                model: drv.get_model().get_name(),
+                stability: drv.stability(),
+            })
+            .collect()
+    }
+
+    pub fn scan_devices(&mut self) -> CommandResult<()> {
+        info!("Scan devices");
+        let Some(context) = &self.context else {
+            return Ok(());
+        };
+        let usb_devices = context.devices().context("listing USB devices")?;
+        let old_devices: Vec<UsbDevice> =
+            self.devices.iter().map(|dev| dev.dev().clone()).collect();
+        self.devices = usb_devices
+            .iter()
+            .filter_map(|device| self.try_open_device(&device))
+            .collect();
+        info!("Found {} device(s)", self.devices.len());
+        let devices = &self.devices;
+        self.initial_colors
+            .retain(|serial, _| devices.iter().any(|dev| dev.serial_number() == serial));
+        for device in &self.devices {
+            if old_devices.contains(device.dev()) {
+                continue;
+            }
+            match device.read_current_color() {
+                Ok(Some(color)) => {
+                    self.initial_colors
+                        .insert(device.serial_number().to_string(), color);
+                }
+                Ok(None) => {}
+                Err(err) => warn!("Reading color of {} failed: {:?}", device, err),
+            }
+        }
+        if old_devices.len() != self.devices.len()
+            || self
+                .devices
+                .iter()
+                .any(|dev| !old_devices.contains(dev.dev()))
+        {
+            self.notify_device_watchers();
+        }
+        Ok(())
+    }
+
+    pub fn watch_devices(&mut self) -> mpsc::Receiver<()> {
+        let (tx, rx) = mpsc::channel();
+        self.device_watchers.push(tx);
+        rx
+    }
+
+    fn notify_device_watchers(&mut self) {
+        self.device_watchers
+            .retain(|watcher| watcher.send(()).is_ok());
+    }
+
+    pub fn load_devices(&mut self) -> CommandResult<()> {
+        self.scan_devices()?;
+        if self.config.apply_on_start() {
+            self.apply_config();
+        } else {
+            info!("Skip applying config on start");
+        }
+        Ok(())
+    }
+
+    fn try_open_device(&self, device: &UsbDevice) -> Option<Box<dyn GDevice>> {
+        if self.dry_run.is_some() {
+            return None;
+        }
+        if let Some(driver) = find_driver_for_device(&self.drivers, device) {
+            info!("Found device {}", driver.get_model().get_name());
+            if !is_driver_allowed(driver, self.config.allow_experimental()) {
+                warn!(
+                    "Driver for {} is experimental and needs `allow_experimental=true` in [gdevd] config section",
+                    driver.get_model().get_name()
+                );
+                return None;
+            }
+            driver.open_device(device)
+        } else {
+            None
+        }
+    }
+
+    pub fn send_command_to(&mut self, serial: &str, cmd: Command) -> CommandResult<()> {
+        let device = self
+            .devices
+            .iter_mut()
+            .find(|device| device.serial_number() == serial)
+            .ok_or_else(|| CommandError::DeviceNotFound(serial.to_string()))?;
+        Self::send_device_command(
+            device,
+            &mut self.config,
+            &mut self.unsaved,
+            cmd,
+            self.brightness_override,
+        )
+    }
+
+    pub fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
+        if cmd.is_lighting() && self.animation.take().is_some() {
+            info!("Software effect replaced by {:?}", cmd);
+        }
+        if let Some(sink) = &self.dry_run {
+            for driver in self.dry_run_drivers() {
+                Self::dry_run_command(
+                    driver,
+                    &**sink,
+                    &self.config,
+                    &cmd,
+                    self.brightness_override,
+                );
+            }
+            return Ok(());
+        }
+        let mut failures = vec![];
+        for device in &mut self.devices {
+            match Self::send_device_command(
+                device,
+                &mut self.config,
+                &mut self.unsaved,
+                cmd.clone(),
+                self.brightness_override,
+            ) {
+                // Broadcasts are meant for devices supporting the command
+                Ok(()) | Err(CommandError::Unsupported { .. }) => {}
+                Err(err) => failures.push((device.to_string(), err)),
+            }
+        }
+        partial_failure(failures)
+    }
+
+    pub fn apply_properties(&mut self, props: &Properties) -> CommandResult<()> {
+        if let Some(kind) = props.get("type") {
+            if !Config::is_command_type(kind) {
+                return Err(CommandError::InvalidArgument(
+                    "type",
+                    format!("unknown command type `{kind}`"),
+                ));
+            }
+        }
+
+        let brightness_override = self.brightness_override;
+        let mut failures = vec![];
+        for device in &mut self.devices {
+            let model = device.get_model();
+            if !self.config.is_enabled(&*model, device.serial_number()) {
+                continue;
+            }
+            if self.config.is_locked(&*model, device.serial_number()) {
+                warn!("Config section ignored for locked device {}", device);
+                continue;
+            }
+
+            let serial = device.serial_number().to_string();
+            let gamma = self.config.gamma(&*model, &serial);
+            let mut cmds = self.config.parse_commands(props, &*model);
+            cmds.retain(|cmd| match check_command(&*model, cmd) {
+                Ok(()) => true,
+                Err(err) => {
+                    error!("Command {:?} ignored for device {}: {:?}", cmd, device, err);
+                    false
+                }
+            });
+            let device_cmds: Vec<Command> = cmds
+                .iter()
+                .map(|cmd| Self::prepare_command(&*model, cmd, gamma, brightness_override))
+                .collect();
+            if device_cmds.is_empty() {
+                continue;
+            }
+            match device.send_command_batch(&device_cmds) {
+                Ok(()) => {}
+                // Broadcasts are meant for devices supporting the commands, which are not
+                // saved for the others
+                Err(CommandError::Unsupported { .. }) => continue,
+                Err(err) => {
+                    error!("Sending commands failed for device {}: {:?}", device, err);
+                    failures.push((device.to_string(), err));
+                    continue;
+                }
+            }
+
+            if cmds.iter().any(|cmd| cmd.is_lighting()) {
+                self.unsaved.remove(&serial);
+                if self.config.is_off(&*model, &serial) {
+                    self.config.set_off(&*model, &serial, false);
+                }
+            }
+            for cmd in cmds {
+                self.config.save_command(&*model, &serial, cmd);
+            }
+        }
+        partial_failure(failures)
+    }
+
+    fn check_command_type(props: &Properties) -> CommandResult<()> {
+        let kind = props.get("type").unwrap_or_default();
+        if !Config::is_command_type(kind) {
+            return Err(CommandError::InvalidArgument(
+                "type",
+                format!("unknown command type `{kind}`"),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn send_properties_to(&mut self, serial: &str, props: &Properties) -> CommandResult<()> {
+        Self::check_command_type(props)?;
+
+        let device = self
+            .devices
+            .iter_mut()
+            .find(|device| device.serial_number() == serial)
+            .ok_or_else(|| CommandError::DeviceNotFound(serial.to_string()))?;
+        let cmds = self.config.parse_commands(props, &*device.get_model());
+        for cmd in cmds {
+            Self::send_device_command(
+                device,
+                &mut self.config,
+                &mut self.unsaved,
+                cmd,
+                self.brightness_override,
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn send_properties(&mut self, props: &Properties) -> CommandResult<()> {
+        Self::check_command_type(props)?;
+
+        let mut failures = vec![];
+        for device in &mut self.devices {
+            let cmds = self.config.parse_commands(props, &*device.get_model());
+            for cmd in cmds {
+                match Self::send_device_command(
+                    device,
+                    &mut self.config,
+                    &mut self.unsaved,
+                    cmd,
+                    self.brightness_override,
+                ) {
+                    Ok(()) | Err(CommandError::Unsupported { .. }) => {}
+                    Err(err) => failures.push((device.to_string(), err)),
+                }
+            }
+        }
+        partial_failure(failures)
+    }
+
+    /// Send command to device and save it to config
+    ///
+    /// Commands are only saved when the device accepted them, so that commands the device
+    /// does not support are not applied again on the next refresh.
+    fn send_device_command(
+        device: &mut GDeviceRef,
+        config: &mut Config,
+        unsaved: &mut HashMap<String, Command>,
+        cmd: Command,
+        brightness_override: Option<Brightness>,
+    ) -> CommandResult<()> {
+        let model = device.get_model();
+        if !config.is_enabled(&*model, device.serial_number()) {
+            info!("Command ignored for disabled device {}", device);
+            return Ok(());
+        }
+        if config.is_locked(&*model, device.serial_number()) {
+            warn!("Command ignored for locked device {}", device);
+            return Ok(());
+        }
+        if let Command::SetFeatureReport(_, _) = cmd {
+            if !config.allow_raw() {
+                error!("Raw feature reports are disabled, set allow_raw in config to enable");
+                return Err(CommandError::Unsupported {
+                    model: model.get_name(),
+                    effect: cmd.name(),
+                });
+            }
+            return device.send_command(cmd).map_err(|err| {
+                error!("Sending raw feature report failed for device: {:?}", err);
+                err
+            });
+        }
+
+        let gamma = config.gamma(&*model, device.serial_number());
+        let device_cmd = Self::prepare_command(&*model, &cmd, gamma, brightness_override);
+        if let Err(err) = check_command(&*model, &cmd).and_then(|_| device.send_command(device_cmd))
+        {
+            error!("Sending command failed for device: {:?}", err);
+            return Err(err);
+        }
+
+        if cmd.is_lighting() {
+            unsaved.remove(device.serial_number());
+            if config.is_off(&*model, device.serial_number()) {
+                config.set_off(&*model, device.serial_number(), false);
+            }
+        }
+        config.save_command(&*model, device.serial_number(), cmd);
+        Ok(())
+    }
+
+    /// Turn lighting of device off or restore saved lighting
+    ///
+    /// Returns whether lighting is on afterwards.
+    pub fn toggle_lighting(&mut self, serial: &str) -> CommandResult<bool> {
+        let device = self
+            .devices
+            .iter_mut()
+            .find(|device| device.serial_number() == serial)
+            .ok_or_else(|| CommandError::DeviceNotFound(serial.to_string()))?;
+        let model = device.get_model();
+        let off = !self.config.is_off(&*model, serial);
+
+        self.config.set_off(&*model, serial, off);
+        Self::apply_device_config(device, &self.config, self.brightness_override);
+        Ok(!off)
+    }
+
+    pub fn start_rainbow(&mut self, period: Speed) {
+        self.animation = Some(Animation::Rainbow(Rainbow {
+            period: Duration::from_millis(period.0 as u64),
+            start: Instant::now(),
+            failed: vec![],
+        }));
+    }
+
+    /// Start fade of static color from saved colors to `to`
+    pub fn start_fade(&mut self, to: RgbColor, sector: Option<u8>, duration: Duration) {
+        self.animation = Some(Animation::Fade(Fade {
+            from: self.saved_colors(sector),
+            to,
+            sector,
+            duration,
+            start: Instant::now(),
+        }));
+    }
+
+    /// Stop software effect and keep its last frame
+    pub fn stop_animation(&mut self) {
+        if self.animation.take().is_some() {
+            info!("Software effect stopped");
+        }
+    }
+
+    fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// Start test pattern on devices that can color their sectors independently
+    pub fn start_test_pattern(&mut self) {
+        let mut serials = vec![];
+        let mut steps = 0;
+        for device in &self.devices {
+            let model = device.get_model();
+            let colors = vec![RgbColor(0, 0, 0); model.get_sectors() as usize];
+            if sector_colors_command(&*model, &colors).is_none() {
+                warn!("Test pattern skipped for {device} without separately colored sectors");
+                continue;
+            }
+            serials.push(device.serial_number().to_string());
+            steps = steps.max(model.get_sectors());
+        }
+
+        self.animation = Some(Animation::TestPattern(TestPattern {
+            serials,
+            steps,
+            shown: None,
+            start: Instant::now(),
+        }));
+    }
+
+    /// Send next frame of software effect to all devices
+    fn animation_step(&mut self) {
+        match self.animation {
+            Some(Animation::Rainbow(_)) => self.rainbow_step(),
+            Some(Animation::Fade(_)) => self.fade_step(),
+            Some(Animation::TestPattern(_)) => self.test_pattern_step(),
+            None => {}
+        }
+    }
+
+    /// Light next sector of test pattern, and restore config after the last one
+    fn test_pattern_step(&mut self) {
+        let Some(Animation::TestPattern(pattern)) = &mut self.animation else {
+            return;
+        };
+
+        let elapsed_steps = pattern.start.elapsed().as_millis() / TEST_PATTERN_STEP.as_millis();
+        let step = elapsed_steps.min(pattern.steps as u128) as u8;
+        if pattern.shown == Some(step) {
+            return;
+        }
+        pattern.shown = Some(step);
+
+        if step >= pattern.steps {
+            if let Some(Animation::TestPattern(pattern)) = self.animation.take() {
+                for serial in pattern.serials {
+                    if let Err(err) = self.apply_config_to(&serial) {
+                        error!("Restoring config failed for device {}: {:?}", serial, err);
+                    }
+                }
+            }
+            return;
+        }
+
+        let serials = pattern.serials.clone();
+        for serial in serials {
+            let Some(model) = self
+                .devices
+                .iter()
+                .find(|device| device.serial_number() == serial)
+                .map(|device| device.get_model())
+            else {
+                continue;
+            };
+            let colors: Vec<RgbColor> = (0..model.get_sectors())
+                .map(|sector| {
+                    if sector <= step {
+                        TEST_PATTERN_COLORS[sector as usize % TEST_PATTERN_COLORS.len()].clone()
+                    } else {
+                        RgbColor(0, 0, 0)
+                    }
+                })
+                .collect();
+            let Some(cmd) = sector_colors_command(&*model, &colors) else {
+                continue;
+            };
+            if let Err(err) = self.send_unsaved_to(&serial, cmd) {
+                error!(
+                    "Sending test pattern failed for device {}: {:?}",
+                    serial, err
+                );
+            }
+        }
+    }
+
+    /// Send next step of fade without saving it, and save the target color at its end
+    fn fade_step(&mut self) {
+        let Some(Animation::Fade(fade)) = &self.animation else {
+            return;
+        };
+
+        let t = fade.progress();
+        if t >= 1.0 {
+            if let Some(Animation::Fade(fade)) = self.animation.take() {
+                if let Err(err) = self.send_command(Command::ColorSector(fade.to, fade.sector)) {
+                    error!("Finishing fade failed: {:?}", err);
+                }
+            }
+            return;
+        }
+
+        let steps: Vec<(String, Command)> = fade
+            .from
+            .iter()
+            .map(|(serial, from)| {
+                let cmd = Command::ColorSector(from.lerp(&fade.to, t), fade.sector);
+                (serial.clone(), cmd)
+            })
+            .collect();
+        for (serial, cmd) in steps {
+            if let Err(err) = self.send_unsaved_to(&serial, cmd) {
+                error!("Sending fade step failed for device {}: {:?}", serial, err);
+            }
+        }
+    }
+
+    /// Send next frame of rainbow to all devices without saving it
+    fn rainbow_step(&mut self) {
+        let Some(Animation::Rainbow(animation)) = &mut self.animation else {
+            return;
+        };
+
+        let brightness_override = self.brightness_override;
+        for device in &mut self.devices {
+            let model = device.get_model();
+            let serial = device.serial_number().to_string();
+            if animation.failed.contains(&serial)
+                || self.config.is_locked(&*model, &serial)
+                || !self.config.is_enabled(&*model, &serial)
+            {
+                continue;
+            }
+            // devices without per-sector colors are skipped instead of showing one color
+            let colors = animation.frame(model.get_sectors());
+            let Some(cmd) = sector_colors_command(&*model, &colors) else {
+                continue;
+            };
+
+            self.unsaved.insert(serial.clone(), cmd.clone());
+            let gamma = self.config.gamma(&*model, &serial);
+            let cmd = Self::prepare_command(&*model, &cmd, gamma, brightness_override);
+            if let Err(err) = device.send_frame(cmd) {
+                error!(
+                    "Sending software effect failed for device {device}, skipping it: {:?}",
+                    err
+                );
+                animation.failed.push(serial);
+            }
+        }
+    }
+
+    pub fn get_capabilities(&self) -> Vec<GDeviceCapabilities> {
+        self.devices
+            .iter()
+            .map(|dev| {
+                let model = dev.get_model();
+                let mut features: Vec<String> = model
+                    .supported_effects()
+                    .iter()
+                    .map(|effect| effect.to_string())
+                    .collect();
+                if model.supports_dpi() {
+                    features.push("dpi".to_string());
+                }
+                if model.supports_triple_color() {
+                    features.push("triple".to_string());
+                }
+                GDeviceCapabilities {
+                    model: model.get_name(),
+                    serial: dev.serial_number().to_string(),
+                    sectors: model.get_sectors(),
+                    features,
+                }
+            })
+            .collect()
+    }
+
+    pub fn get_state(&self) -> Vec<GDeviceState> {
+        self.devices
+            .iter()
+            .map(|dev| {
+                let model = dev.get_model();
+                let mut effect = self.config.describe(&*model, dev.serial_number());
+                if effect == "default" {
+                    if let Some(color) = self.initial_colors.get(dev.serial_number()) {
+                        effect = format!("device color={}", color.to_hex());
+                    }
+                }
+                GDeviceState {
+                    model: model.get_name(),
+                    serial: dev.serial_number().to_string(),
+                    effect,
+                }
+            })
+            .collect()
+    }
+
+    pub fn get_debug_info(&self) -> Vec<(String, String)> {
+        self.devices
+            .iter()
+            .map(|dev| (dev.serial_number().to_string(), dev.get_debug_info()))
+            .collect()
+    }
 
-            self.config.save_command(&*device.get_model(), cmd.clone())
+    pub fn config_warnings(&self) -> Vec<String> {
+        let models: Vec<GDeviceModelRef> = self.drivers.iter().map(|drv| drv.get_model()).collect();
+        self.config.check(&models)
+    }
+
+    pub fn get_supported_effects(&mut self, from_device: bool) -> Vec<(String, Vec<EffectKind>)> {
+        self.devices
+            .iter_mut()
+            .map(|device| {
+                let queried = if from_device {
+                    device.query_supported_effects().unwrap_or_else(|err| {
+                        error!("Reading effects of {device} failed: {:?}", err);
+                        None
+                    })
+                } else {
+                    None
+                };
+                let effects =
+                    queried.unwrap_or_else(|| device.get_model().supported_effects().to_vec());
+                (device.serial_number().to_string(), effects)
+            })
+            .collect()
+    }
+
+    pub fn get_onboard_profiles(&mut self) -> Vec<(String, u8)> {
+        self.devices
+            .iter_mut()
+            .filter_map(|device| match device.get_onboard_profile() {
+                Ok(slot) => slot.map(|slot| (device.serial_number().to_string(), slot)),
+                Err(err) => {
+                    error!("Reading onboard profile of {device} failed: {:?}", err);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn probe_features(&mut self, serial: &str) -> CommandResult<Vec<(u8, u16)>> {
+        let device = self
+            .devices
+            .iter_mut()
+            .find(|device| device.serial_number() == serial)
+            .ok_or_else(|| CommandError::DeviceNotFound(serial.to_string()))?;
+        device.probe_features()
+    }
+
+    pub fn get_dpi_stages(&self) -> Vec<(String, u8)> {
+        self.devices
+            .iter()
+            .map(|device| {
+                (
+                    device.serial_number().to_string(),
+                    device.get_model().num_dpi_stages(),
+                )
+            })
+            .filter(|(_, stages)| *stages > 0)
+            .collect()
+    }
+
+    pub fn push_frame(&mut self, colors: &[RgbColor]) -> CommandResult<()> {
+        let mut matched = false;
+        for device in &mut self.devices {
+            if device.get_model().get_sectors() as usize != colors.len() {
+                debug!("Frame with {} colors skipped for {}", colors.len(), device);
+                continue;
+            }
+
+            let model = device.get_model();
+            if self.config.is_locked(&*model, device.serial_number())
+                || !self.config.is_enabled(&*model, device.serial_number())
+            {
+                debug!("Frame ignored for locked or disabled device {}", device);
+                continue;
+            }
+
+            matched = true;
+            self.unsaved.insert(
+                device.serial_number().to_string(),
+                Command::ColorSectors(colors.to_vec()),
+            );
+            for (sector, color) in colors.iter().enumerate() {
+                let cmd = Command::ColorSector(color.clone(), Some(sector as u8));
+                let model = device.get_model();
+                let gamma = self.config.gamma(&*model, device.serial_number());
+                let cmd = Self::prepare_command(&*model, &cmd, gamma, self.brightness_override);
+                if let Err(err) = device.send_command(cmd) {
+                    error!("Sending frame failed for device {device}: {:?}", err);
+                }
+            }
+        }
+
+        if matched {
+            Ok(())
+        } else {
+            Err(CommandError::InvalidArgument(
+                "colors",
+                format!("no device with {} sectors", colors.len()),
+            ))
+        }
+    }
+
+    /// Save lighting that was only shown, like frames or software effects, to config
+    pub fn save(&mut self) {
+        for device in &self.devices {
+            let serial = device.serial_number();
+            if let Some(cmd) = self.unsaved.remove(serial) {
+                let model = device.get_model();
+                info!("Saving current lighting of {}", device);
+                if self.config.is_off(&*model, serial) {
+                    self.config.set_off(&*model, serial, false);
+                }
+                self.config.save_command(&*model, serial, cmd);
+            }
+        }
+    }
+
+    /// Return saved static color of sector for each device with static colors
+    pub fn saved_colors(&self, sector: Option<u8>) -> Vec<(String, RgbColor)> {
+        self.devices
+            .iter()
+            .filter_map(|device| {
+                let serial = device.serial_number();
+                let color = self
+                    .config
+                    .commands_for(&*device.get_model(), serial)
+                    .into_iter()
+                    .find_map(|cmd| match cmd {
+                        Command::ColorSector(color, None) => Some(color),
+                        Command::ColorSector(color, Some(saved)) if Some(saved) == sector => {
+                            Some(color)
+                        }
+                        _ => None,
+                    })?;
+                Some((serial.to_string(), color))
+            })
+            .collect()
+    }
+
+    /// Send command to device without saving it to config
+    pub fn send_unsaved_to(&mut self, serial: &str, cmd: Command) -> CommandResult<()> {
+        let device = self
+            .devices
+            .iter_mut()
+            .find(|device| device.serial_number() == serial)
+            .ok_or_else(|| CommandError::DeviceNotFound(serial.to_string()))?;
+        let model = device.get_model();
+        if self.config.is_locked(&*model, serial) || !self.config.is_enabled(&*model, serial) {
+            debug!("Command ignored for locked or disabled device {}", device);
+            return Ok(());
         }
+
+        let gamma = self.config.gamma(&*model, serial);
+        let cmd = Self::prepare_command(&*model, &cmd, gamma, self.brightness_override);
+        device.send_command(cmd)
     }
 
     fn apply_config(&mut self) {
+        if let Some(sink) = &self.dry_run {
+            for driver in self.dry_run_drivers() {
+                let model = driver.get_model();
+                for cmd in Self::config_commands(&self.config, &*model, "") {
+                    Self::dry_run_command(
+                        driver,
+                        &**sink,
+                        &self.config,
+                        &cmd,
+                        self.brightness_override,
+                    );
+                }
+            }
+            return;
+        }
+        self.unsaved.clear();
         for device in &mut self.devices {
-            Self::apply_device_config(device, &self.config);
+            Self::apply_device_config(device, &self.config, self.brightness_override);
         }
     }
 
-    fn apply_device_config(device: &mut GDeviceRef, config: &Config) {
-        info!("Setting config for {}", device.get_model().get_name());
-        for command in config.commands_for(&*device.get_model()) {
-            if let Err(err) = device.send_command(command.clone()) {
-                error!("Unable to send command to device {device}: {:?}", err);
+    /// Drivers that would be allowed to claim devices
+    fn dry_run_drivers(&self) -> impl Iterator<Item = &dyn GDeviceDriver> {
+        let allow_experimental = self.config.allow_experimental();
+        self.drivers
+            .iter()
+            .map(|driver| driver.deref())
+            .filter(move |driver| is_driver_allowed(*driver, allow_experimental))
+    }
+
+    fn dry_run_command(
+        driver: &dyn GDeviceDriver,
+        sink: &dyn CommandSink,
+        config: &Config,
+        cmd: &Command,
+        brightness_override: Option<Brightness>,
+    ) {
+        let model = driver.get_model();
+        info!("Dry run of {:?} for {}", cmd, model.get_name());
+        let gamma = config.gamma(&*model, "");
+        let device_cmd = Self::prepare_command(&*model, cmd, gamma, brightness_override);
+        if let Err(err) = check_command(&*model, cmd).and_then(|_| driver.dry_run(sink, device_cmd))
+        {
+            error!("Command would fail for {}: {:?}", model.get_name(), err);
+        }
+    }
+
+    pub fn apply_profile(&mut self, name: &str) {
+        if !self.config.has_profile(name) {
+            warn!("Unknown profile {} ignored", name);
+            return;
+        }
+
+        self.config.set_active_profile(name);
+        self.apply_config();
+    }
+
+    fn apply_config_to(&mut self, serial: &str) -> CommandResult<()> {
+        let device = self
+            .devices
+            .iter_mut()
+            .find(|device| device.serial_number() == serial)
+            .ok_or_else(|| CommandError::DeviceNotFound(serial.to_string()))?;
+        self.unsaved.remove(serial);
+        Self::apply_device_config(device, &self.config, self.brightness_override);
+        Ok(())
+    }
+
+    pub fn set_locked(&mut self, serial: &str, locked: bool) -> CommandResult<()> {
+        let device = self
+            .devices
+            .iter()
+            .find(|device| device.serial_number() == serial)
+            .ok_or_else(|| CommandError::DeviceNotFound(serial.to_string()))?;
+        self.config.set_locked(&*device.get_model(), serial, locked);
+        Ok(())
+    }
+
+    fn apply_device_config(
+        device: &mut GDeviceRef,
+        config: &Config,
+        brightness_override: Option<Brightness>,
+    ) {
+        let model = device.get_model();
+        if !config.is_enabled(&*model, device.serial_number()) {
+            info!("Skip config for disabled device {device}");
+            return;
+        }
+        info!("Setting config for {}", model.get_name());
+        if config.is_off(&*model, device.serial_number()) {
+            if let Err(err) = device.send_command(Command::ColorSector(RgbColor(0, 0, 0), None)) {
+                error!("Unable to turn off lighting of device {device}: {:?}", err);
+            }
+            return;
+        }
+
+        let gamma = config.gamma(&*model, device.serial_number());
+        let commands: Vec<Command> = Self::config_commands(config, &*model, device.serial_number())
+            .iter()
+            .map(|command| Self::prepare_command(&*model, command, gamma, brightness_override))
+            .collect();
+        if commands.is_empty() {
+            return;
+        }
+        if let Err(err) = device.send_command_batch(&commands) {
+            error!("Unable to send commands to device {device}: {:?}", err);
+        }
+    }
+
+    /// Commands of config for device, with the effect before its power-up behavior
+    fn config_commands(config: &Config, model: &dyn GDeviceModel, serial: &str) -> Vec<Command> {
+        let mut commands = config.commands_for(model, serial);
+        // Effect has to be set before choosing whether it is replayed on power-up
+        commands.sort_by_key(|command| matches!(command, Command::StartEffect(_)));
+        commands
+    }
+
+    /// Adapt command to device model and runtime state like gamma and brightness override
+    fn prepare_command(
+        model: &dyn GDeviceModel,
+        cmd: &Command,
+        gamma: Option<f32>,
+        brightness_override: Option<Brightness>,
+    ) -> Command {
+        let cmd = cmd.adapted_to(model);
+        let cmd = match gamma {
+            Some(gamma) => cmd.gamma_corrected(gamma),
+            None => cmd,
+        };
+        match brightness_override {
+            Some(factor) => cmd.dimmed(factor),
+            None => cmd,
+        }
+    }
+
+    pub fn set_brightness_override(&mut self, brightness: Option<Brightness>) {
+        self.brightness_override = brightness;
+        self.apply_config();
+    }
+
+    pub fn poll_events(&mut self) {
+        for device in &mut self.devices {
+            for event in device.poll_events() {
+                debug!("Event from device {}: {:?}", device, event);
+                match event {
+                    DeviceEvent::BrightnessChanged(brightness) => {
+                        let model = device.get_model();
+                        let serial = device.serial_number();
+                        for cmd in self.config.commands_for(&*model, serial) {
+                            if let Some(cmd) = cmd.with_brightness(brightness) {
+                                self.config.save_command(&*model, serial, cmd);
+                            }
+                        }
+                    }
+                }
             }
         }
     }
 
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        self.config.refresh_interval()
+    }
+
     pub fn refresh(&mut self) {
         info!("Refreshing");
-        self.config = Config::load();
+        self.config = Config::load_from(self.config.path());
+        self.config_warnings();
         self.apply_config();
     }
 
@@ -352,13 +2223,15 @@ impl GDeviceManagerState {
                 warn!("Plugged in device {} already exists", gdev)
             } else {
                 info!("Device plugged in: {}", gdev);
-                Self::apply_device_config(&mut gdev, &self.config);
+                Self::apply_device_config(&mut gdev, &self.config, self.brightness_override);
                 self.devices.push(gdev);
+                self.notify_device_watchers();
             }
         }
     }
 
     pub fn on_lost_usb_device(&mut self, dev: UsbDevice) {
+        let count = self.devices.len();
         self.devices.retain(|existing| {
             if existing.dev() == &dev {
                 info!("Device unplugged: {}", existing);
@@ -367,6 +2240,9 @@ impl GDeviceManagerState {
                 true
             }
         });
+        if self.devices.len() != count {
+            self.notify_device_watchers();
+        }
     }
 }
 
@@ -379,8 +2255,35 @@ pub struct GDeviceManager {
 impl GDeviceManager {
     /// Try to create device manager with USB connection
     pub fn try_new() -> CommandResult<Self> {
+        Self::try_with_config(Config::load())
+    }
+
+    /// Try to create device manager with USB connection and config
+    ///
+    /// With `GDEVD_DRY_RUN=1` in the environment no device is opened and the reports of
+    /// commands are logged for each driver instead.
+    pub fn try_with_config(config: Config) -> CommandResult<Self> {
+        let dry_run = env::var_os(DRY_RUN_ENV).is_some_and(|value| value == "1");
+        if dry_run {
+            info!("Dry run: reports are logged instead of sent to devices");
+            return Self::try_with_sink(config, DryRunSink);
+        }
+        Self::try_with_dry_run(config, None)
+    }
+
+    /// Try to create device manager that sends reports to `sink` instead of devices
+    ///
+    /// Like a dry run, the reports of each command are sent for every allowed driver.
+    pub fn try_with_sink(config: Config, sink: impl CommandSink + 'static) -> CommandResult<Self> {
+        Self::try_with_dry_run(config, Some(Box::new(sink)))
+    }
+
+    fn try_with_dry_run(
+        config: Config,
+        dry_run: Option<Box<dyn CommandSink>>,
+    ) -> CommandResult<Self> {
         let (tx, rx) = mpsc::sync_channel(1024);
-        let state = GDeviceManagerState::new(tx.clone())?;
+        let state = GDeviceManagerState::new(tx.clone(), config, dry_run)?;
         Ok(Self {
             tx,
             rx: Mutex::new(rx),
@@ -388,7 +2291,8 @@ impl GDeviceManager {
         })
     }
 
-    pub fn context(&self) -> Context {
+    /// USB context for event handling, which dry runs do not have
+    pub fn context(&self) -> Option<Context> {
         self.state().context.clone()
     }
 
@@ -396,42 +2300,269 @@ impl GDeviceManager {
         &self.tx
     }
 
+    /// Receive a message whenever devices are connected or removed
+    pub fn watch_devices(&self) -> mpsc::Receiver<()> {
+        self.state().watch_devices()
+    }
+
+    /// Open supported devices and apply config unless disabled
     pub fn load_devices(&self) -> CommandResult<()> {
         self.state().load_devices()
     }
 
+    /// Open supported devices without applying config
+    pub fn scan_devices(&self) -> CommandResult<()> {
+        self.state().scan_devices()
+    }
+
     /// Send command to all devices
     pub fn list(&self) -> Vec<GDeviceInfo> {
         self.state().get_devices()
     }
 
     /// Send command to all devices
-    pub fn list_drivers(&self) -> Vec<&'static str> {
+    pub fn list_drivers(&self) -> Vec<GDriverInfo> {
         self.state().get_drivers()
     }
 
     /// Send command to all devices
-    pub fn send_command(&self, cmd: Command) {
+    ///
+    /// Devices are updated even when the command fails for some of them; those failures
+    /// are returned as `CommandError::PartialFailure`.
+    pub fn send_command(&self, cmd: Command) -> CommandResult<()> {
+        if let Command::TestPattern = cmd {
+            let _ = self.tx.send(GDeviceManagerEvent::StartTestPattern);
+            return Ok(());
+        }
         self.state().send_command(cmd)
     }
 
+    /// Send static color command to all devices with a fade from their saved color
+    ///
+    /// The fade runs in `run()` and is at most `MAX_FADE_DURATION` long. The color is saved
+    /// when it ends, which is also when devices without saved static color change. Other
+    /// commands are sent instantly.
+    pub fn send_command_smooth(&self, cmd: Command, duration: Duration) -> CommandResult<()> {
+        match cmd {
+            Command::ColorSector(to, sector) if !duration.is_zero() => {
+                let duration = duration.min(MAX_FADE_DURATION);
+                let _ = self
+                    .tx
+                    .send(GDeviceManagerEvent::StartFade(to, sector, duration));
+                Ok(())
+            }
+            cmd => self.send_command(cmd),
+        }
+    }
+
+    /// Send command to device with serial number
+    pub fn send_command_to(&self, serial: &str, cmd: Command) -> CommandResult<()> {
+        self.state().send_command_to(serial, cmd)
+    }
+
+    /// Return sector count and supported features of each device
+    pub fn get_capabilities(&self) -> Vec<GDeviceCapabilities> {
+        self.state().get_capabilities()
+    }
+
+    /// Return current effect of each device
+    pub fn get_state(&self) -> Vec<GDeviceState> {
+        self.state().get_state()
+    }
+
+    /// Return debug information for each device
+    pub fn get_debug_info(&self) -> Vec<(String, String)> {
+        self.state().get_debug_info()
+    }
+
+    /// Check config and return warnings about ignored or invalid entries
+    pub fn config_warnings(&self) -> Vec<String> {
+        self.state().config_warnings()
+    }
+
+    /// Return supported effects for each device
+    ///
+    /// With `from_device` the effects are read from the firmware when the device supports it.
+    pub fn get_supported_effects(&self, from_device: bool) -> Vec<(String, Vec<EffectKind>)> {
+        self.state().get_supported_effects(from_device)
+    }
+
+    /// Read HID++ feature index and ID of each feature of device with serial number
+    pub fn probe_features(&self, serial: &str) -> CommandResult<Vec<(u8, u16)>> {
+        self.state().probe_features(serial)
+    }
+
+    /// Return active onboard profile slot for each device with onboard profiles
+    pub fn get_onboard_profiles(&self) -> Vec<(String, u8)> {
+        self.state().get_onboard_profiles()
+    }
+
+    /// Return maximum number of DPI stages for each device supporting DPI stages
+    pub fn get_dpi_stages(&self) -> Vec<(String, u8)> {
+        self.state().get_dpi_stages()
+    }
+
+    /// Send all commands of a config section to all devices at once
+    ///
+    /// Unlike `send_properties` each device is reset only once, so multiple sector colors
+    /// and effect settings are applied without flicker.
+    pub fn apply_properties(&self, props: &Properties) -> CommandResult<()> {
+        self.state().apply_properties(props)
+    }
+
+    /// Send command described by config properties to all devices
+    ///
+    /// Properties are the same as in a device section of the config file.
+    pub fn send_properties(&self, props: &Properties) -> CommandResult<()> {
+        self.state().send_properties(props)
+    }
+
+    /// Send command described by config properties to device with serial number
+    pub fn send_properties_to(&self, serial: &str, props: &Properties) -> CommandResult<()> {
+        self.state().send_properties_to(serial, props)
+    }
+
+    /// Show a frame of per-sector colors on all devices with matching sector count
+    ///
+    /// Frames are transient and are not saved to config.
+    pub fn push_frame(&self, colors: &[RgbColor]) -> CommandResult<()> {
+        self.state().push_frame(colors)
+    }
+
+    /// Save lighting shown by frames or software effects to config
+    ///
+    /// Lighting sent by other commands is already saved.
+    pub fn save(&self) {
+        self.state().save()
+    }
+
     /// Send current config to device
-    pub fn apply_config(&mut self) {
+    pub fn apply_config(&self) {
         self.state().apply_config()
     }
 
+    /// Make named profile active and send its config to devices
+    ///
+    /// Profiles are config sections named `[<model>:<profile>]`. Unknown profiles are ignored.
+    pub fn apply_profile(&self, name: &str) {
+        self.state().apply_profile(name)
+    }
+
     /// Refresh config from filesystem and send config
     pub fn refresh(&self) {
         self.state().refresh()
     }
 
+    /// Turn lighting of device off or restore saved lighting
+    ///
+    /// Returns whether lighting is on afterwards. State is saved in config.
+    pub fn toggle_lighting(&self, serial: &str) -> CommandResult<bool> {
+        self.state().toggle_lighting(serial)
+    }
+
+    /// Lock device against changes by commands or unlock it again
+    ///
+    /// Lock state is saved in config for the model of the device.
+    pub fn set_locked(&self, serial: &str, locked: bool) -> CommandResult<()> {
+        self.state().set_locked(serial, locked)
+    }
+
+    /// Send current config to device with serial number
+    pub fn apply_config_to(&self, serial: &str) -> CommandResult<()> {
+        self.state().apply_config_to(serial)
+    }
+
+    /// Read events from devices and update config to match hardware state
+    pub fn poll_events(&self) {
+        self.state().poll_events()
+    }
+
+    /// Temporarily scale brightness of all devices without changing config
+    ///
+    /// `None` removes the override and restores the saved brightness.
+    pub fn set_brightness_override(&self, brightness: Option<Brightness>) {
+        self.state().set_brightness_override(brightness)
+    }
+
+    /// Start rainbow across sectors animated by daemon
+    ///
+    /// `speed` is the time in milliseconds for a full hue rotation. The animation runs in
+    /// `run()` until another lighting command is sent or it is stopped.
+    pub fn start_software_rainbow(&self, speed: Speed) {
+        let _ = self.tx.send(GDeviceManagerEvent::StartRainbow(speed));
+    }
+
+    /// Stop software effect and keep its last frame
+    ///
+    /// Does nothing when no software effect is running.
+    pub fn stop_software_effect(&self) {
+        let _ = self.tx.send(GDeviceManagerEvent::StopAnimation);
+    }
+
     pub fn run(&self) {
-        while let Ok(msg) = self.rx.lock().unwrap().recv() {
+        let mut next_refresh = self
+            .state()
+            .refresh_interval()
+            .map(|interval| Instant::now() + interval);
+        loop {
+            let rx = self.rx.lock().unwrap();
+            let animating = self.state().is_animating();
+            let timeout = match (animating, next_refresh) {
+                (true, _) => Some(FADE_STEP),
+                (false, Some(at)) => Some(at.saturating_duration_since(Instant::now())),
+                (false, None) => None,
+            };
+
+            let msg = if let Some(timeout) = timeout {
+                match rx.recv_timeout(timeout) {
+                    Ok(msg) => msg,
+                    Err(RecvTimeoutError::Timeout) => {
+                        drop(rx);
+                        if animating {
+                            self.state().animation_step();
+                        } else if next_refresh.is_some_and(|at| at <= Instant::now()) {
+                            // Workaround for firmware forgetting lighting after bus resets
+                            debug!("Periodic refresh");
+                            let mut state = self.state();
+                            state.apply_config();
+                            next_refresh = state
+                                .refresh_interval()
+                                .map(|interval| Instant::now() + interval);
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            } else {
+                match rx.recv() {
+                    Ok(msg) => msg,
+                    Err(_) => break,
+                }
+            };
+            drop(rx);
+
             match msg {
                 GDeviceManagerEvent::DevicePluggedIn(dev) => self.state().on_new_usb_device(dev),
                 GDeviceManagerEvent::DevicePluggedOut(dev) => self.state().on_lost_usb_device(dev),
+                GDeviceManagerEvent::Resumed => {
+                    info!("Resumed from suspend");
+                    self.state().refresh()
+                }
+                GDeviceManagerEvent::StartRainbow(speed) => self.state().start_rainbow(speed),
+                GDeviceManagerEvent::StopAnimation => self.state().stop_animation(),
+                GDeviceManagerEvent::StartFade(to, sector, duration) => {
+                    self.state().start_fade(to, sector, duration)
+                }
+                GDeviceManagerEvent::StartTestPattern => self.state().start_test_pattern(),
                 GDeviceManagerEvent::Shutdown => break,
             }
+
+            // Config may have been reloaded
+            next_refresh = match (next_refresh, self.state().refresh_interval()) {
+                (_, None) => None,
+                (None, Some(interval)) => Some(Instant::now() + interval),
+                (next_refresh, Some(_)) => next_refresh,
+            };
         }
     }
 
@@ -465,3 +2596,282 @@ impl Hotplug<Context> for HotPlugHandler {
         self.send(GDeviceManagerEvent::DevicePluggedOut(device));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::g203_lightsync::G203LightsyncModel;
+    use crate::drivers::g213::G213Model;
+    use crate::drivers::g413::G413Model;
+    use crate::drivers::{padded, RecordingSink};
+
+    #[test]
+    fn direction_round_trip() {
+        for direction in [
+            Direction::LeftToRight,
+            Direction::RightToLeft,
+            Direction::CenterToEdge,
+            Direction::EdgeToCenter,
+        ] {
+            assert_eq!(
+                Direction::try_from(direction.to_string().as_str()),
+                Ok(direction)
+            );
+        }
+    }
+
+    #[test]
+    fn shorthand_hex_color_is_expanded() {
+        assert_eq!(RgbColor::from_hex("f00"), Ok(RgbColor(0xff, 0x00, 0x00)));
+        assert_eq!(RgbColor::from_hex("0a0"), Ok(RgbColor(0x00, 0xaa, 0x00)));
+    }
+
+    #[test]
+    fn hex_color_of_invalid_length_is_rejected() {
+        assert!(RgbColor::from_hex("ff").is_err());
+    }
+
+    #[test]
+    fn start_effect_is_applied_after_effect() {
+        let sink = RecordingSink::default();
+        let config =
+            Config::from_str("[G213]\nstart_effect=false\ntype=static-all\ncolor=ff0000\n");
+        let manager = GDeviceManager::try_with_sink(config, sink.clone()).unwrap();
+        manager.apply_config();
+
+        let reset = padded(&[0x11, 0xff, 0x0c, 0x0d]);
+        assert_eq!(
+            sink.reports(),
+            vec![
+                reset.clone(),
+                padded(&[0x11, 0xff, 0x0c, 0x3a, 0x00, 0x01, 0xff, 0x00, 0x00, 0x02]),
+                reset,
+                padded(&[0x11, 0xff, 0x0c, 0x5d, 0x00, 0x01, 0x02]),
+            ]
+        );
+    }
+
+    #[test]
+    fn gamma_darkens_mid_gray() {
+        assert_eq!(
+            RgbColor(0x80, 0x80, 0x80).apply_gamma(2.2),
+            RgbColor(0x38, 0x38, 0x38)
+        );
+    }
+
+    #[test]
+    fn gamma_keeps_black_and_white() {
+        assert_eq!(RgbColor(0, 0, 0).apply_gamma(2.2), RgbColor(0, 0, 0));
+        assert_eq!(
+            RgbColor(0xff, 0xff, 0xff).apply_gamma(2.2),
+            RgbColor(0xff, 0xff, 0xff)
+        );
+    }
+
+    #[test]
+    fn scale_color() {
+        assert_eq!(
+            RgbColor(0xff, 0x80, 0x00).scale(0.3),
+            RgbColor(0x4d, 0x26, 0x00)
+        );
+    }
+
+    #[test]
+    fn scale_color_clamps_factor() {
+        assert_eq!(
+            RgbColor(0x10, 0x20, 0x30).scale(2.0),
+            RgbColor(0x10, 0x20, 0x30)
+        );
+        assert_eq!(RgbColor(0x10, 0x20, 0x30).scale(-1.0), RgbColor(0, 0, 0));
+    }
+
+    #[test]
+    fn scale_color_with_white() {
+        assert_eq!(
+            RgbwColor(0xff, 0x00, 0x00, 0x64).scale(0.5),
+            RgbwColor(0x80, 0x00, 0x00, 0x32)
+        );
+    }
+
+    /// Model counting speeds in steps of 10 ms
+    struct CoarseSpeedModel;
+
+    impl GDeviceModel for CoarseSpeedModel {
+        fn get_sectors(&self) -> u8 {
+            1
+        }
+
+        fn get_default_color(&self) -> RgbColor {
+            RgbColor(0xff, 0xff, 0xff)
+        }
+
+        fn get_name(&self) -> &'static str {
+            "Coarse"
+        }
+
+        fn get_type(&self) -> DeviceType {
+            DeviceType::Mouse
+        }
+
+        fn usb_product_id(&self) -> u16 {
+            0
+        }
+
+        fn speed_unit_millis(&self) -> u16 {
+            10
+        }
+    }
+
+    #[test]
+    fn speed_is_converted_to_device_units() {
+        assert_eq!(
+            GDeviceManagerState::prepare_command(
+                &CoarseSpeedModel,
+                &Command::Breathe(RgbColor(1, 2, 3), Some(Speed(1000)), None),
+                None,
+                None
+            ),
+            Command::Breathe(RgbColor(1, 2, 3), Some(Speed(100)), None)
+        );
+        assert_eq!(
+            GDeviceManagerState::prepare_command(
+                &CoarseSpeedModel,
+                &Command::Reactive(RgbColor(1, 2, 3), Speed(500)),
+                None,
+                None
+            ),
+            Command::Reactive(RgbColor(1, 2, 3), Speed(50))
+        );
+    }
+
+    /// Device recording commands it receives
+    struct CommandRecorder {
+        sent: Arc<Mutex<Vec<Command>>>,
+    }
+
+    impl Display for CommandRecorder {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("recorder")
+        }
+    }
+
+    impl GDevice for CommandRecorder {
+        fn dev(&self) -> &UsbDevice {
+            unreachable!("recorder has no USB device")
+        }
+
+        fn serial_number(&self) -> &str {
+            "1"
+        }
+
+        fn get_model(&self) -> GDeviceModelRef {
+            Arc::new(G213Model)
+        }
+
+        fn send_command(&mut self, cmd: Command) -> CommandResult<()> {
+            self.sent.lock().unwrap().push(cmd);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn adapted_device_adapts_commands_to_model() {
+        let sent = Arc::new(Mutex::new(vec![]));
+        let mut device = AdaptedDevice::new(Box::new(CommandRecorder { sent: sent.clone() }));
+        device
+            .send_command(Command::ColorSectorRgbw(RgbwColor(1, 2, 3, 4), Some(9)))
+            .unwrap();
+        device
+            .send_command_batch(&[Command::Gradient(
+                RgbColor(0, 0, 0),
+                RgbColor(0xff, 0xff, 0xff),
+            )])
+            .unwrap();
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent[0], Command::ColorSector(RgbColor(1, 2, 3), None));
+        assert!(matches!(&sent[1], Command::ColorSectors(colors) if colors.len() == 5));
+    }
+
+    #[test]
+    fn adapted_device_checks_commands() {
+        let sent = Arc::new(Mutex::new(vec![]));
+        let mut device = AdaptedDevice::new(Box::new(CommandRecorder { sent: sent.clone() }));
+        let result = device.send_command(Command::ColorSectors(vec![RgbColor(0, 0, 0)]));
+
+        assert!(matches!(
+            result,
+            Err(CommandError::InvalidArgument("colors", _))
+        ));
+        assert!(sent.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn missing_sector_is_downgraded_to_all_sectors() {
+        assert_eq!(
+            GDeviceManagerState::prepare_command(
+                &G213Model,
+                &Command::ColorSector(RgbColor(1, 2, 3), Some(5)),
+                None,
+                None
+            ),
+            Command::ColorSector(RgbColor(1, 2, 3), None)
+        );
+    }
+
+    #[test]
+    fn missing_sector_of_rgbw_color_is_downgraded_to_all_sectors() {
+        assert_eq!(
+            GDeviceManagerState::prepare_command(
+                &G213Model,
+                &Command::ColorSectorRgbw(RgbwColor(1, 2, 3, 4), Some(9)),
+                None,
+                None
+            ),
+            Command::ColorSector(RgbColor(1, 2, 3), None)
+        );
+    }
+
+    #[test]
+    fn existing_sector_is_kept() {
+        assert_eq!(
+            GDeviceManagerState::prepare_command(
+                &G213Model,
+                &Command::ColorSector(RgbColor(1, 2, 3), Some(4)),
+                None,
+                None
+            ),
+            Command::ColorSector(RgbColor(1, 2, 3), Some(4))
+        );
+    }
+
+    #[test]
+    fn sector_colors_of_zoned_model() {
+        let colors = vec![RgbColor(1, 0, 0); 5];
+        assert_eq!(
+            sector_colors_command(&G213Model, &colors),
+            Some(Command::ColorSectors(colors))
+        );
+    }
+
+    #[test]
+    fn sector_colors_of_triple_color_model() {
+        let colors = [RgbColor(1, 0, 0), RgbColor(2, 0, 0), RgbColor(3, 0, 0)];
+        assert_eq!(
+            sector_colors_command(&G203LightsyncModel, &colors),
+            Some(Command::TripleColor(
+                RgbColor(1, 0, 0),
+                RgbColor(2, 0, 0),
+                RgbColor(3, 0, 0)
+            ))
+        );
+    }
+
+    #[test]
+    fn sector_colors_of_single_sector_model() {
+        assert_eq!(
+            sector_colors_command(&G413Model, &[RgbColor(1, 0, 0)]),
+            Some(Command::ColorSector(RgbColor(1, 0, 0), None))
+        );
+    }
+}